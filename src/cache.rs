@@ -0,0 +1,172 @@
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use failure::Error;
+use log::{debug, error};
+use serde_derive::{Deserialize, Serialize};
+
+use rspotify::spotify::model::playlist::{PlaylistTrack, SimplifiedPlaylist};
+use rspotify::spotify::model::track::FullTrack;
+use rspotify::spotify::model::user::PublicUser;
+
+use crate::config;
+
+// an entry of a playlist, referring to its track by id
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Entry {
+    added_at: DateTime<Utc>,
+    added_by: Option<PublicUser>,
+    is_local: bool,
+    track_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Snapshot {
+    snapshot_id: String,
+    entries: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Metadata {
+    playlists: Option<Vec<SimplifiedPlaylist>>,
+    // the tracks of the playlists, by id of the playlist
+    snapshots: HashMap<String, Snapshot>,
+    tracks: HashMap<String, FullTrack>,
+}
+
+/// Keeps the playlists of the user and their tracks on disk, so they can be
+/// shown before they have been fetched again. The tracks of a playlist are
+/// valid as long as the snapshot id of the playlist stays the same.
+pub struct MetadataCache {
+    path: Option<PathBuf>,
+    metadata: Mutex<Metadata>,
+}
+
+impl MetadataCache {
+    pub fn load() -> MetadataCache {
+        let path = config::cache_dir().map(|dir| dir.join("metadata.json"));
+        let metadata = path
+            .as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                fs::read(path)
+                    .map_err(Error::from)
+                    .and_then(|data| serde_json::from_slice(&data).map_err(Error::from))
+                    .map_err(|e| error!("could not load metadata cache: {}", e))
+                    .ok()
+            })
+            .unwrap_or_default();
+        MetadataCache {
+            path,
+            metadata: Mutex::new(metadata),
+        }
+    }
+
+    pub fn playlists(&self) -> Option<Vec<SimplifiedPlaylist>> {
+        self.metadata.lock().unwrap().playlists.clone()
+    }
+
+    /// Stores the playlists of the user, dropping the tracks of playlists
+    /// that are gone.
+    pub fn set_playlists(&self, playlists: &[SimplifiedPlaylist]) {
+        let mut metadata = self.metadata.lock().unwrap();
+        metadata
+            .snapshots
+            .retain(|id, _| playlists.iter().any(|playlist| playlist.id == *id));
+        metadata.playlists = Some(playlists.to_vec());
+        prune_tracks(&mut metadata);
+        self.save(&metadata);
+    }
+
+    /// Returns the tracks of a playlist if they are cached for its current
+    /// snapshot.
+    pub fn playlist_tracks(&self, playlist: &SimplifiedPlaylist) -> Option<Vec<PlaylistTrack>> {
+        let metadata = self.metadata.lock().unwrap();
+        let snapshot = metadata.snapshots.get(&playlist.id)?;
+        if snapshot.snapshot_id != playlist.snapshot_id {
+            return None;
+        }
+        snapshot
+            .entries
+            .iter()
+            .map(|entry| {
+                Some(PlaylistTrack {
+                    added_at: entry.added_at,
+                    added_by: entry.added_by.clone(),
+                    is_local: entry.is_local,
+                    track: metadata.tracks.get(&entry.track_id)?.clone(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn set_playlist_tracks(&self, playlist: &SimplifiedPlaylist, tracks: &[PlaylistTrack]) {
+        let mut metadata = self.metadata.lock().unwrap();
+        let entries = tracks
+            .iter()
+            .map(|playlist_track| Entry {
+                added_at: playlist_track.added_at,
+                added_by: playlist_track.added_by.clone(),
+                is_local: playlist_track.is_local,
+                track_id: playlist_track.track.id.clone(),
+            })
+            .collect();
+        for playlist_track in tracks {
+            metadata.tracks.insert(
+                playlist_track.track.id.clone(),
+                playlist_track.track.clone(),
+            );
+        }
+        metadata.snapshots.insert(
+            playlist.id.clone(),
+            Snapshot {
+                snapshot_id: playlist.snapshot_id.clone(),
+                entries,
+            },
+        );
+        prune_tracks(&mut metadata);
+        self.save(&metadata);
+    }
+
+    fn save(&self, metadata: &Metadata) {
+        if let Some(ref path) = self.path {
+            match write(path, metadata) {
+                Ok(()) => debug!("saved metadata cache"),
+                Err(e) => error!("could not save metadata cache: {}", e),
+            }
+        }
+    }
+}
+
+fn write(path: &Path, metadata: &Metadata) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // written to a temporary file first, so an interrupted write doesn't
+    // break the cache
+    let partial = path.with_extension("part");
+    fs::write(&partial, serde_json::to_vec(metadata)?)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+// drops the tracks no cached playlist refers to anymore
+fn prune_tracks(metadata: &mut Metadata) {
+    let referenced: HashSet<&String> = metadata
+        .snapshots
+        .values()
+        .flat_map(|snapshot| snapshot.entries.iter().map(|entry| &entry.track_id))
+        .collect();
+    let unused: Vec<String> = metadata
+        .tracks
+        .keys()
+        .filter(|id| !referenced.contains(id))
+        .cloned()
+        .collect();
+    for id in unused {
+        metadata.tracks.remove(&id);
+    }
+}
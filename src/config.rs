@@ -1,6 +1,8 @@
 use serde_derive::{Deserialize, Serialize};
+use std::env;
 use std::fs::File;
 use std::io::prelude::*;
+use std::path::PathBuf;
 
 pub const CLIENT_ID: &str = "d420a117a32841c2b3474932e49fb54b";
 
@@ -8,6 +10,108 @@ pub const CLIENT_ID: &str = "d420a117a32841c2b3474932e49fb54b";
 pub struct Config {
     pub username: String,
     pub password: String,
+    #[serde(default)]
+    pub play_mode: PlayMode,
+    pub statusbar_format: Option<String>,
+    pub theme: Option<ThemeConfig>,
+    #[serde(default)]
+    pub cover: CoverMode,
+    pub notifications: Option<NotificationConfig>,
+    pub hooks: Option<HookConfig>,
+    pub scrobbler: Option<ScrobblerConfig>,
+    pub cache: Option<CacheConfig>,
+    pub crossfade_ms: Option<u32>,
+    pub backend: Option<String>,
+    pub device: Option<String>,
+}
+
+/// The cache of librespot, keeping the credentials and the audio files that
+/// have been played. It is enabled unless `enabled` is `false`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CacheConfig {
+    pub enabled: Option<bool>,
+    pub directory: Option<String>,
+    pub audio: Option<bool>,
+    pub size_limit_mb: Option<u64>,
+}
+
+/// A ListenBrainz compatible service plays are submitted to. `url` defaults
+/// to the one of ListenBrainz itself.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScrobblerConfig {
+    pub url: Option<String>,
+    pub token: String,
+}
+
+/// Shell commands run on changes of the player. The details of the track are
+/// passed in environment variables.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HookConfig {
+    pub on_song_change: Option<String>,
+    pub on_play: Option<String>,
+    pub on_pause: Option<String>,
+    pub on_stop: Option<String>,
+}
+
+/// Desktop notifications shown when the track changes. `summary` and `body`
+/// are templates with the placeholders `{title}`, `{artist}`, `{album}` and
+/// `{duration}`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct NotificationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    pub summary: Option<String>,
+    pub body: Option<String>,
+}
+
+/// How the cover art of the playing track is drawn: with half blocks, showing
+/// two pixels per character, with braille patterns, showing eight dots in one
+/// colour, or not at all.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverMode {
+    HalfBlock,
+    Braille,
+    None,
+}
+
+impl Default for CoverMode {
+    fn default() -> Self {
+        CoverMode::HalfBlock
+    }
+}
+
+/// Colours of the user interface. `name` selects one of the built-in themes,
+/// the other settings override single colours of it. Colours are given by
+/// name (e.g. `"light blue"`), as hex value (`"#ff8800"`), as number of the
+/// 256 colour palette or as `"default"` for the colour of the terminal.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ThemeConfig {
+    pub name: Option<String>,
+    pub background: Option<String>,
+    pub primary: Option<String>,
+    pub secondary: Option<String>,
+    pub title: Option<String>,
+    pub highlight: Option<String>,
+    pub highlight_inactive: Option<String>,
+    pub statusbar: Option<String>,
+    pub statusbar_bg: Option<String>,
+    pub playing: Option<String>,
+}
+
+/// What happens when a track of a playlist is played: either only the track
+/// itself is played, or the rest of the playlist is queued after it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayMode {
+    Single,
+    Context,
+}
+
+impl Default for PlayMode {
+    fn default() -> Self {
+        PlayMode::Context
+    }
 }
 
 pub fn load(filename: &str) -> Result<Config, toml::de::Error> {
@@ -18,3 +122,13 @@ pub fn load(filename: &str) -> Result<Config, toml::de::Error> {
 
     toml::from_str(&contents)
 }
+
+/// Returns the directory ncspot keeps its caches in, `$XDG_CACHE_HOME/ncspot`
+/// or `~/.cache/ncspot`.
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+    };
+    Some(cache.join("ncspot"))
+}
@@ -0,0 +1,440 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use librespot::playback::audio_backend::Sink;
+use log::error;
+
+// librespot plays interleaved stereo at 44.1 kHz
+const SAMPLE_RATE: usize = 44_100;
+const CHANNELS: usize = 2;
+
+// samples an input buffers at most before its player has to wait, a fifth of
+// a second. Pausing and seeking take effect after the buffered samples.
+const BUFFER: usize = SAMPLE_RATE * CHANNELS / 5;
+// samples written to the sink at once at most
+const CHUNK: usize = 4096;
+
+struct Input {
+    samples: VecDeque<i16>,
+    // whether the player of the input is playing
+    active: bool,
+    gain: f32,
+    // change of the gain per sample while fading
+    step: f32,
+    // whether the input started while the other one was still playing
+    // without a fade, in which case it follows once the other one is done
+    waiting: bool,
+    // whether the input fades in but its player hasn't written samples yet,
+    // e.g. while loading the track. The fade starts with the first samples.
+    loading: bool,
+}
+
+impl Input {
+    fn new() -> Input {
+        Input {
+            samples: VecDeque::new(),
+            active: false,
+            gain: 1.0,
+            step: 0.0,
+            waiting: false,
+            loading: false,
+        }
+    }
+
+    fn playing(&self) -> bool {
+        self.active || !self.samples.is_empty()
+    }
+}
+
+struct State {
+    inputs: [Input; 2],
+    // length of a fade, tracks follow each other without fading if it is 0
+    fade_samples: usize,
+}
+
+impl State {
+    // mixes the next samples of both inputs, or returns `None` if there
+    // are none. The inputs of active players advance together, so one that
+    // falls behind holds the other one back rather than having its samples
+    // mixed in early. An input that is still loading doesn't hold back the
+    // other one. Stopped inputs play out what they have buffered.
+    fn mix(&mut self) -> Option<Vec<i16>> {
+        for i in 0..2 {
+            if self.inputs[i].waiting && !self.inputs[1 - i].playing() {
+                self.inputs[i].waiting = false;
+            }
+        }
+
+        let active = self
+            .inputs
+            .iter()
+            .filter(|input| input.active && !input.waiting && !input.loading)
+            .map(|input| input.samples.len())
+            .min();
+        let len = active
+            .unwrap_or_else(|| {
+                self.inputs
+                    .iter()
+                    .filter(|input| !input.waiting)
+                    .map(|input| input.samples.len())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .min(CHUNK);
+        if len == 0 {
+            return None;
+        }
+
+        let mut mixed = vec![0f32; len];
+        for input in self.inputs.iter_mut().filter(|input| !input.waiting) {
+            let n = len.min(input.samples.len());
+            for (out, sample) in mixed.iter_mut().zip(input.samples.drain(..n)) {
+                *out += f32::from(sample) * input.gain;
+                input.gain = (input.gain + input.step).max(0.0).min(1.0);
+            }
+        }
+        Some(
+            mixed
+                .into_iter()
+                .map(|sample| {
+                    sample
+                        .max(f32::from(std::i16::MIN))
+                        .min(f32::from(std::i16::MAX)) as i16
+                })
+                .collect(),
+        )
+    }
+}
+
+type Shared = Arc<(Mutex<State>, Condvar)>;
+
+/// Mixes the output of two players into one sink, going over from one to the
+/// other. Once an input starts while the other one is still playing, the
+/// other one fades out while the new one fades in. Without a fade, the new one
+/// starts right after the other one has played out what it has buffered.
+pub struct Mixer {
+    shared: Shared,
+}
+
+impl Mixer {
+    pub fn new<F>(fade_ms: u32, sink_builder: F) -> Mixer
+    where
+        F: FnOnce() -> Box<dyn Sink> + Send + 'static,
+    {
+        let state = State {
+            inputs: [Input::new(), Input::new()],
+            fade_samples: fade_ms as usize * SAMPLE_RATE / 1000 * CHANNELS,
+        };
+        let shared = Arc::new((Mutex::new(state), Condvar::new()));
+        {
+            let shared = shared.clone();
+            thread::spawn(move || output(&shared, sink_builder()));
+        }
+        Mixer { shared }
+    }
+
+    /// Returns the sink of one of the two inputs, `index` being 0 or 1.
+    pub fn input(&self, index: usize) -> MixerInput {
+        MixerInput {
+            shared: self.shared.clone(),
+            index,
+        }
+    }
+}
+
+// writes the mixed samples to the sink, which is started while any input
+// is playing
+fn output(shared: &Shared, mut sink: Box<dyn Sink>) {
+    let (ref lock, ref condvar) = **shared;
+    let mut started = false;
+    loop {
+        let chunk = {
+            let mut state = lock.lock().unwrap();
+            let chunk = loop {
+                if let Some(chunk) = state.mix() {
+                    break Some(chunk);
+                }
+                if started && !state.inputs.iter().any(|input| input.active) {
+                    break None;
+                }
+                state = condvar.wait(state).unwrap();
+            };
+            // the players may write again
+            condvar.notify_all();
+            chunk
+        };
+
+        let result = match chunk {
+            Some(chunk) if !started => {
+                started = true;
+                sink.start().and_then(|_| sink.write(&chunk))
+            }
+            Some(chunk) => sink.write(&chunk),
+            None => {
+                started = false;
+                sink.stop()
+            }
+        };
+        if let Err(e) = result {
+            error!("could not write to audio sink: {}", e);
+        }
+    }
+}
+
+/// The sink a player writes to, one of the inputs of a `Mixer`.
+pub struct MixerInput {
+    shared: Shared,
+    index: usize,
+}
+
+impl Sink for MixerInput {
+    fn start(&mut self) -> io::Result<()> {
+        let (ref lock, ref condvar) = *self.shared;
+        let mut state = lock.lock().unwrap();
+        let fade_samples = state.fade_samples as f32;
+        let (this, other) = (self.index, 1 - self.index);
+
+        // starting while the other input plays goes over to the new track,
+        // anything else is a new track after a stop or the same one after a
+        // pause
+        if state.inputs[other].playing() && !state.inputs[this].playing() {
+            if fade_samples > 0.0 {
+                state.inputs[this].gain = 0.0;
+                state.inputs[this].step = 1.0 / fade_samples;
+                state.inputs[this].loading = true;
+            } else {
+                state.inputs[this].gain = 1.0;
+                state.inputs[this].step = 0.0;
+                state.inputs[this].waiting = true;
+            }
+        } else if !state.inputs[other].playing() {
+            state.inputs[this].gain = 1.0;
+            state.inputs[this].step = 0.0;
+        }
+        state.inputs[this].active = true;
+        condvar.notify_all();
+        Ok(())
+    }
+
+    fn stop(&mut self) -> io::Result<()> {
+        let (ref lock, ref condvar) = *self.shared;
+        let mut state = lock.lock().unwrap();
+        state.inputs[self.index].active = false;
+        state.inputs[self.index].loading = false;
+        condvar.notify_all();
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[i16]) -> io::Result<()> {
+        let (ref lock, ref condvar) = *self.shared;
+        let mut state = lock.lock().unwrap();
+        // players decode faster than the audio is played, so they wait for
+        // the buffered samples to be played
+        while state.inputs[self.index].samples.len() >= BUFFER {
+            state = condvar.wait(state).unwrap();
+        }
+        if state.inputs[self.index].loading && !data.is_empty() {
+            // the other input fades out along with this one fading in
+            state.inputs[self.index].loading = false;
+            let step = state.inputs[self.index].step;
+            state.inputs[1 - self.index].step = -step;
+        }
+        state.inputs[self.index].samples.extend(data);
+        condvar.notify_all();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shared(fade_samples: usize) -> Shared {
+        let state = State {
+            inputs: [Input::new(), Input::new()],
+            fade_samples,
+        };
+        Arc::new((Mutex::new(state), Condvar::new()))
+    }
+
+    fn input(samples: &[i16], active: bool) -> Input {
+        let mut input = Input::new();
+        input.samples.extend(samples);
+        input.active = active;
+        input
+    }
+
+    #[test]
+    fn mix_adds_inputs() {
+        let mut state = State {
+            inputs: [input(&[1, 2, 3], true), input(&[10, 20, 30], true)],
+            fade_samples: 1,
+        };
+        assert_eq!(state.mix(), Some(vec![11, 22, 33]));
+        assert_eq!(state.mix(), None);
+    }
+
+    #[test]
+    fn mix_waits_for_active_input_behind() {
+        let mut state = State {
+            inputs: [input(&[1, 1, 1, 1], true), input(&[2, 2], true)],
+            fade_samples: 1,
+        };
+        assert_eq!(state.mix(), Some(vec![3, 3]));
+        // the samples of the first input are kept until the second one
+        // catches up
+        assert_eq!(state.mix(), None);
+        state.inputs[1].samples.extend(&[4, 4]);
+        assert_eq!(state.mix(), Some(vec![5, 5]));
+    }
+
+    #[test]
+    fn mix_plays_out_stopped_input() {
+        let mut state = State {
+            inputs: [input(&[1, 1], false), input(&[2, 2, 2], true)],
+            fade_samples: 1,
+        };
+        assert_eq!(state.mix(), Some(vec![3, 3, 2]));
+
+        let mut state = State {
+            inputs: [input(&[1, 1, 1], false), input(&[], false)],
+            fade_samples: 1,
+        };
+        assert_eq!(state.mix(), Some(vec![1, 1, 1]));
+    }
+
+    #[test]
+    fn mix_clamps_samples() {
+        let mut state = State {
+            inputs: [
+                input(&[30_000, -30_000], true),
+                input(&[30_000, -30_000], true),
+            ],
+            fade_samples: 1,
+        };
+        assert_eq!(state.mix(), Some(vec![std::i16::MAX, std::i16::MIN]));
+    }
+
+    #[test]
+    fn start_without_other_input_plays_at_full_gain() {
+        let shared = shared(4);
+        MixerInput {
+            shared: shared.clone(),
+            index: 0,
+        }
+        .start()
+        .unwrap();
+
+        let state = shared.0.lock().unwrap();
+        assert!(state.inputs[0].active);
+        assert_eq!(state.inputs[0].gain, 1.0);
+        assert_eq!(state.inputs[0].step, 0.0);
+    }
+
+    #[test]
+    fn start_while_other_input_plays_crossfades() {
+        let shared = shared(4);
+        {
+            let mut state = shared.0.lock().unwrap();
+            state.inputs[0] = input(&[1000; 8], true);
+        }
+        MixerInput {
+            shared: shared.clone(),
+            index: 1,
+        }
+        .start()
+        .unwrap();
+
+        {
+            let state = shared.0.lock().unwrap();
+            assert_eq!(state.inputs[1].gain, 0.0);
+            assert_eq!(state.inputs[1].step, 0.25);
+            // the other input fades out once this one has samples
+            assert_eq!(state.inputs[0].step, 0.0);
+        }
+        MixerInput {
+            shared: shared.clone(),
+            index: 1,
+        }
+        .write(&[1000; 8])
+        .unwrap();
+
+        // the gains ramp over the fade and stay at their ends after it
+        let mut state = shared.0.lock().unwrap();
+        assert_eq!(state.inputs[0].step, -0.25);
+        assert_eq!(
+            state.mix(),
+            Some(vec![1000, 1000, 1000, 1000, 1000, 1000, 1000, 1000])
+        );
+        assert_eq!(state.inputs[0].gain, 0.0);
+        assert_eq!(state.inputs[1].gain, 1.0);
+    }
+
+    #[test]
+    fn mix_plays_on_while_new_input_loads() {
+        let shared = shared(4);
+        {
+            let mut state = shared.0.lock().unwrap();
+            state.inputs[0] = input(&[1000; 4], true);
+        }
+        MixerInput {
+            shared: shared.clone(),
+            index: 1,
+        }
+        .start()
+        .unwrap();
+
+        // the new input is active but has no samples yet
+        let mut state = shared.0.lock().unwrap();
+        assert!(state.inputs[1].active);
+        assert_eq!(state.mix(), Some(vec![1000, 1000, 1000, 1000]));
+        assert_eq!(state.inputs[0].gain, 1.0);
+    }
+
+    #[test]
+    fn start_without_fade_follows_other_input() {
+        let shared = shared(0);
+        {
+            let mut state = shared.0.lock().unwrap();
+            // the previous track ended and plays out its buffer
+            state.inputs[0] = input(&[1, 1], false);
+        }
+        MixerInput {
+            shared: shared.clone(),
+            index: 1,
+        }
+        .start()
+        .unwrap();
+
+        let mut state = shared.0.lock().unwrap();
+        state.inputs[1].samples.extend(&[2, 2, 2]);
+        assert_eq!(state.mix(), Some(vec![1, 1]));
+        assert_eq!(state.mix(), Some(vec![2, 2, 2]));
+        assert_eq!(state.mix(), None);
+    }
+
+    #[test]
+    fn start_while_fading_in_keeps_gain() {
+        let shared = shared(4);
+        {
+            let mut state = shared.0.lock().unwrap();
+            state.inputs[0] = input(&[1000; 8], true);
+            state.inputs[1] = input(&[1000; 8], true);
+            state.inputs[1].gain = 0.5;
+            state.inputs[1].step = 0.25;
+        }
+        // e.g. resuming the track that is fading in
+        MixerInput {
+            shared: shared.clone(),
+            index: 1,
+        }
+        .start()
+        .unwrap();
+
+        let state = shared.0.lock().unwrap();
+        assert_eq!(state.inputs[1].gain, 0.5);
+        assert_eq!(state.inputs[1].step, 0.25);
+    }
+}
@@ -4,6 +4,7 @@ use derive_more::Display;
 
 use crate::spotify::PlayerState;
 
+use rspotify::spotify::model::playlist::SimplifiedPlaylist;
 use rspotify::spotify::model::track::FullTrack;
 
 #[derive(Display)]
@@ -18,6 +19,8 @@ pub enum Event {
     CheckQueue,
     #[display(fmt = "Event::SeekTo")]
     SeekTo(u32),
+    #[display(fmt = "Event::SeekToPercent")]
+    SeekToPercent(usize),
     #[display(fmt = "Event::SeekForward")]
     SeekForward(u32),
     #[display(fmt = "Event::SeekBackward")]
@@ -28,6 +31,20 @@ pub enum Event {
     QueueRemove(usize),
     #[display(fmt = "Event::SongChange")]
     SongChange(FullTrack),
+    #[display(fmt = "Event::PlaylistsUpdate")]
+    PlaylistsUpdate,
+    #[display(fmt = "Event::PlaylistsLoaded")]
+    PlaylistsLoaded(Vec<SimplifiedPlaylist>),
+    #[display(fmt = "Event::PlaylistUpdate")]
+    PlaylistUpdate(String, String),
+    #[display(fmt = "Event::ShowScreen")]
+    ShowScreen(usize),
+    #[display(fmt = "Event::NavigateBack")]
+    NavigateBack,
+    #[display(fmt = "Event::ShowArtist")]
+    ShowArtist(String),
+    #[display(fmt = "Event::ShowAlbum")]
+    ShowAlbum(String),
     // #[display(fmt = "Event::SongFinish")]
     // SongFinish,
 }
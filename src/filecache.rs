@@ -0,0 +1,135 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use log::{debug, error, info};
+
+use crate::config::{self, CacheConfig};
+
+const DEFAULT_SIZE_LIMIT_MB: u64 = 1024;
+
+/// The disk usage of the cache.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Usage {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// The directory librespot keeps the credentials and the audio files in.
+/// librespot doesn't limit the size of the audio files, so the least
+/// recently used ones are removed once they take up more than the limit.
+#[derive(Clone, Debug)]
+pub struct FileCache {
+    pub dir: PathBuf,
+    pub audio: bool,
+    pub size_limit: u64,
+}
+
+impl FileCache {
+    /// Returns `None` if the cache is disabled or there is no directory for
+    /// it.
+    pub fn new(cfg: &Option<CacheConfig>) -> Option<FileCache> {
+        let cfg = cfg.clone().unwrap_or_default();
+        if cfg.enabled == Some(false) {
+            return None;
+        }
+        let dir = match cfg.directory {
+            Some(dir) => PathBuf::from(dir),
+            None => config::cache_dir()?.join("librespot"),
+        };
+        Some(FileCache {
+            dir,
+            audio: cfg.audio.unwrap_or(true),
+            size_limit: cfg.size_limit_mb.unwrap_or(DEFAULT_SIZE_LIMIT_MB) * 1024 * 1024,
+        })
+    }
+
+    fn files_dir(&self) -> PathBuf {
+        self.dir.join("files")
+    }
+
+    pub fn has_credentials(&self) -> bool {
+        self.dir.join("credentials.json").exists()
+    }
+
+    pub fn usage(&self) -> Usage {
+        let files = audio_files(&self.files_dir());
+        Usage {
+            files: files.len(),
+            bytes: files.iter().map(|file| file.1).sum(),
+        }
+    }
+
+    /// Removes the least recently used audio files until the rest fits into
+    /// the size limit.
+    pub fn evict(&self) {
+        let mut files = audio_files(&self.files_dir());
+        let mut bytes: u64 = files.iter().map(|file| file.1).sum();
+        if bytes <= self.size_limit {
+            return;
+        }
+
+        // the access time is only updated lazily by most file systems, but
+        // still tells which files have been played lately
+        files.sort_by_key(|file| file.2);
+        let mut removed = 0;
+        for (path, size, _) in files {
+            if bytes <= self.size_limit {
+                break;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    debug!("removed {} from the cache", path.display());
+                    bytes -= size;
+                    removed += 1;
+                }
+                Err(e) => error!("could not remove {}: {}", path.display(), e),
+            }
+        }
+        info!("removed {} files from the audio cache", removed);
+    }
+}
+
+// returns the path, size and last access of the files below `dir`
+fn audio_files(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let mut files = Vec::new();
+    if let Err(e) = walk(dir, &mut files) {
+        if e.kind() != io::ErrorKind::NotFound {
+            error!("could not read cache {}: {}", dir.display(), e);
+        }
+    }
+    files
+}
+
+fn walk(dir: &Path, files: &mut Vec<(PathBuf, u64, SystemTime)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            walk(&entry.path(), files)?;
+        } else {
+            let used = metadata
+                .accessed()
+                .or_else(|_| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((entry.path(), metadata.len(), used));
+        }
+    }
+    Ok(())
+}
+
+/// Formats a number of bytes for humans, e.g. `12.3 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
@@ -0,0 +1,54 @@
+//! Data shared by the unit tests.
+
+use rspotify::spotify::model::track::FullTrack;
+use serde_json::json;
+
+/// A track by two artists, as the Web API returns it.
+pub fn track() -> FullTrack {
+    let artist = |id: &str, name: &str| {
+        json!({
+            "external_urls": { "spotify": format!("https://open.spotify.com/artist/{}", id) },
+            "href": format!("https://api.spotify.com/v1/artists/{}", id),
+            "id": id,
+            "name": name,
+            "type": "artist",
+            "uri": format!("spotify:artist:{}", id),
+        })
+    };
+    serde_json::from_value(json!({
+        "album": {
+            "album_type": "album",
+            "artists": [artist("artist1", "First Artist")],
+            "available_markets": ["DE"],
+            "external_urls": { "spotify": "https://open.spotify.com/album/album1" },
+            "href": "https://api.spotify.com/v1/albums/album1",
+            "id": "album1",
+            "images": [],
+            "name": "The Album",
+            "release_date": "2019-03-01",
+            "release_date_precision": "day",
+            "type": "album",
+            "uri": "spotify:album:album1",
+        },
+        "artists": [
+            artist("artist1", "First Artist"),
+            artist("artist2", "Second Artist"),
+        ],
+        "available_markets": ["DE"],
+        "disc_number": 1,
+        "duration_ms": 200_000,
+        "explicit": false,
+        "external_ids": { "isrc": "DEXX11900001" },
+        "external_urls": { "spotify": "https://open.spotify.com/track/track1" },
+        "href": "https://api.spotify.com/v1/tracks/track1",
+        "id": "track1",
+        "is_local": false,
+        "name": "The Title",
+        "popularity": 50,
+        "preview_url": null,
+        "track_number": 3,
+        "type": "track",
+        "uri": "spotify:track:track1",
+    }))
+    .expect("invalid track")
+}
@@ -0,0 +1,190 @@
+use std::process::Command;
+use std::thread::{self, JoinHandle};
+
+use log::{error, trace};
+
+use rspotify::spotify::model::track::FullTrack;
+
+use crate::config::HookConfig;
+use crate::spotify::PlayerState;
+use crate::ui::tracklist::join_artists;
+
+/// Runs the commands configured for changes of the player, e.g. to update a
+/// status bar or log what was played. Commands run in the background and
+/// their failures are only logged.
+pub struct Hooks {
+    cfg: HookConfig,
+    // the player announces its state again on every track change, which
+    // doesn't run the commands again
+    state: PlayerState,
+}
+
+impl Hooks {
+    pub fn new(cfg: Option<HookConfig>) -> Hooks {
+        Hooks {
+            cfg: cfg.unwrap_or_default(),
+            state: PlayerState::Stopped,
+        }
+    }
+
+    pub fn song_change(&self, track: &FullTrack) {
+        run("song_change", &self.cfg.on_song_change, Some(track));
+    }
+
+    /// Runs the command of a new state, with the track that is played, or was
+    /// played before the player stopped. Nothing is run unless the state
+    /// changed.
+    pub fn play_state(&mut self, state: PlayerState, track: Option<&FullTrack>) {
+        if let Some((event, command)) = self.state_hook(state) {
+            run(event, command, track);
+        }
+    }
+
+    // returns the event and the command for a state, or `None` if the state
+    // didn't change
+    fn state_hook(&mut self, state: PlayerState) -> Option<(&'static str, &Option<String>)> {
+        if state == self.state {
+            return None;
+        }
+        self.state = state;
+        Some(match state {
+            PlayerState::Playing => ("play", &self.cfg.on_play),
+            PlayerState::Paused => ("pause", &self.cfg.on_pause),
+            PlayerState::Stopped => ("stop", &self.cfg.on_stop),
+        })
+    }
+}
+
+// the environment variables passing the details of a track to the commands
+fn track_env(track: &FullTrack) -> Vec<(&'static str, String)> {
+    vec![
+        ("NCSPOT_TRACK_ID", track.id.clone()),
+        ("NCSPOT_TRACK_URI", track.uri.clone()),
+        ("NCSPOT_TITLE", track.name.clone()),
+        ("NCSPOT_ARTIST", join_artists(track)),
+        ("NCSPOT_ALBUM", track.album.name.clone()),
+        ("NCSPOT_DURATION_MS", track.duration_ms.to_string()),
+        ("NCSPOT_TRACK_NUMBER", track.track_number.to_string()),
+    ]
+}
+
+// runs a command in the background and returns the thread waiting for it,
+// which tells whether the command succeeded
+fn run(
+    event: &'static str,
+    command: &Option<String>,
+    track: Option<&FullTrack>,
+) -> Option<JoinHandle<bool>> {
+    let command = command.as_ref()?;
+
+    let mut child = Command::new("sh");
+    child.arg("-c").arg(command).env("NCSPOT_EVENT", event);
+    if let Some(track) = track {
+        child.envs(track_env(track));
+    }
+
+    trace!("running {} hook: {}", event, command);
+    let mut child = match child.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            error!("could not run {} hook: {}", event, e);
+            return None;
+        }
+    };
+
+    // wait in the background, so the result can be logged
+    Some(thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => {
+            error!("{} hook failed: {}", event, status);
+            false
+        }
+        Ok(_) => true,
+        Err(e) => {
+            error!("{} hook failed: {}", event, e);
+            false
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    fn command(command: &str) -> Option<String> {
+        Some(command.to_string())
+    }
+
+    #[test]
+    fn track_details_are_passed_in_environment() {
+        let env = track_env(&fixtures::track());
+        let value = |name| {
+            env.iter()
+                .find(|&&(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(value("NCSPOT_TRACK_ID"), Some("track1"));
+        assert_eq!(value("NCSPOT_TRACK_URI"), Some("spotify:track:track1"));
+        assert_eq!(value("NCSPOT_TITLE"), Some("The Title"));
+        assert_eq!(value("NCSPOT_ARTIST"), Some("First Artist, Second Artist"));
+        assert_eq!(value("NCSPOT_ALBUM"), Some("The Album"));
+        assert_eq!(value("NCSPOT_DURATION_MS"), Some("200000"));
+        assert_eq!(value("NCSPOT_TRACK_NUMBER"), Some("3"));
+    }
+
+    #[test]
+    fn command_sees_event_and_track() {
+        let path = env::temp_dir().join(format!("ncspot-hook-{}", process::id()));
+        let hook = command(&format!(
+            "printf '%s %s' \"$NCSPOT_EVENT\" \"$NCSPOT_TITLE\" > '{}'",
+            path.display()
+        ));
+        let succeeded = run("play", &hook, Some(&fixtures::track()))
+            .unwrap()
+            .join()
+            .unwrap();
+        let output = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(succeeded);
+        assert_eq!(output, "play The Title");
+    }
+
+    #[test]
+    fn states_map_to_their_hooks_on_change_only() {
+        let mut hooks = Hooks::new(Some(HookConfig {
+            on_song_change: command("song"),
+            on_play: command("play"),
+            on_pause: command("pause"),
+            on_stop: None,
+        }));
+        assert_eq!(
+            hooks.state_hook(PlayerState::Playing),
+            Some(("play", &command("play")))
+        );
+        // the player announces the state again on a track change
+        assert_eq!(hooks.state_hook(PlayerState::Playing), None);
+        assert_eq!(
+            hooks.state_hook(PlayerState::Paused),
+            Some(("pause", &command("pause")))
+        );
+        assert_eq!(
+            hooks.state_hook(PlayerState::Stopped),
+            Some(("stop", &None))
+        );
+        assert_eq!(hooks.state_hook(PlayerState::Stopped), None);
+    }
+
+    #[test]
+    fn failing_commands_are_not_fatal() {
+        let failed = run("stop", &command("exit 1"), None);
+        assert!(!failed.unwrap().join().unwrap());
+
+        let missing = run("stop", &command("ncspot-missing-hook-command"), None);
+        assert!(!missing.unwrap().join().unwrap());
+
+        assert!(run("stop", &None, None).is_none());
+    }
+}
@@ -1,31 +1,40 @@
+use std::cell::RefCell;
 use std::env;
 use std::fs::OpenOptions;
 use std::io::prelude::*;
 use std::path::PathBuf;
 use std::process;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::thread;
 
-use log::trace;
+use log::{error, trace};
 
-use cursive::align;
-use cursive::event::Key;
-use cursive::view::Boxable;
+use cursive::event::{Event as CursiveEvent, Key};
 use cursive::view::ScrollStrategy;
 use cursive::views::*;
-use cursive::Cursive;
+use cursive::{Cursive, ScreenId};
 
 use librespot::core::spotify_id::SpotifyId;
 
 use rspotify::spotify::model::track::FullTrack;
 
+mod cache;
 mod config;
+mod crossfade;
 mod events;
+mod filecache;
+#[cfg(test)]
+mod fixtures;
+mod hooks;
+mod notification;
 mod queue;
+mod scrobbler;
 mod spotify;
 mod theme;
 mod ui;
+mod wav;
 
 use crate::events::{Event, EventManager};
 
@@ -53,6 +62,26 @@ fn init_logger(content: TextContent) {
     }
 }
 
+// marks the track that is playing in all lists
+fn show_playing(
+    cursive: &mut Cursive,
+    spotify: &spotify::Spotify,
+    playlist_screen: ScreenId,
+    track: Option<&FullTrack>,
+) {
+    spotify.set_current_track(track);
+
+    // the other lists look the track up when they are drawn, but the
+    // playlist tree can only be reached on its own screen
+    let active_screen = cursive.active_screen();
+    cursive.set_screen(playlist_screen);
+    ui::playlist::PlaylistView::show_playing(cursive, track);
+    cursive.set_screen(active_screen);
+}
+
+// how long before the end of a track the next one is preloaded
+const PRELOAD_MS: u32 = 30_000;
+
 fn main() {
     let logbuf = TextContent::new("Welcome to ncspot\n");
     let logview = TextView::new_with_content(logbuf.clone());
@@ -61,29 +90,69 @@ fn main() {
 
     init_logger(logbuf);
 
-    let mut cursive = Cursive::default();
-    let event_manager = EventManager::new(cursive.cb_sink().clone());
-
-    cursive.add_global_callback('q', |s| s.quit());
-    cursive.set_theme(theme::default());
-
     let path = match env::var_os("HOME") {
         None => {
-            println!("$HOME not set.");
+            eprintln!("$HOME not set.");
             process::exit(1)
         }
         Some(path) => PathBuf::from(format!("{0}/.config/ncspot", path.into_string().unwrap())),
     };
 
     let cfg = config::load(path.to_str().unwrap()).expect("could not load configuration file");
+
+    // the backend is checked before the user interface takes over the
+    // terminal, so the error stays readable
+    if let Some(ref backend) = cfg.backend {
+        if backend == "pipe" || backend == "wav" {
+            match cfg.device {
+                Some(ref device) => {
+                    if let Err(e) = wav::check_device(device) {
+                        eprintln!("The {} backend can't write to {}: {}", backend, device, e);
+                        process::exit(1)
+                    }
+                }
+                None => {
+                    eprintln!("The {} backend needs a device to write to.", backend);
+                    process::exit(1)
+                }
+            }
+        }
+    }
+
+    let mut cursive = Cursive::default();
+    let event_manager = EventManager::new(cursive.cb_sink().clone());
+
+    cursive.add_global_callback('q', |s| s.quit());
+    cursive.set_theme(theme::load(&cfg.theme));
+
+    // <ctrl-r> reloads the theme from the configuration file
+    {
+        let path = path.clone();
+        cursive.add_global_callback(CursiveEvent::CtrlChar('r'), move |s| {
+            match config::load(path.to_str().unwrap()) {
+                Ok(cfg) => s.set_theme(theme::load(&cfg.theme)),
+                Err(e) => error!("could not reload configuration: {}", e),
+            }
+        });
+    }
     let queue = Arc::new(Mutex::new(queue::Queue::new(event_manager.clone())));
 
+    let file_cache = filecache::FileCache::new(&cfg.cache);
+    if let Some(ref file_cache) = file_cache {
+        let file_cache = file_cache.clone();
+        thread::spawn(move || file_cache.evict());
+    }
+
     let spotify = Arc::new(spotify::Spotify::new(
         event_manager.clone(),
         cfg.username,
         cfg.password,
         config::CLIENT_ID.to_string(),
         queue.clone(),
+        file_cache.clone(),
+        cfg.crossfade_ms,
+        cfg.backend,
+        cfg.device,
     ));
 
     // global player keybindings (play, pause, stop)
@@ -119,98 +188,105 @@ fn main() {
         });
     }
 
-    let mut track_info = TextContent::new("");
-    let mut playback_info = TextContent::new("");
-    let counter = cursive::utils::Counter::new(0);
-
-    let make_statusbar = || {
-        LinearLayout::horizontal()
-            .child(TextView::new_with_content(track_info.clone()).h_align(align::HAlign::Left))
-            // .child(DummyView.full_width())
-            .child(DummyView)
-            .child(ProgressBar::new().with_value(counter.clone()).full_width())
-            .child(DummyView)
-            .child(TextView::new_with_content(playback_info.clone()).h_align(align::HAlign::Right))
+    let status = Arc::new(Mutex::new(ui::statusbar::Status::new()));
+    let statusbar_format = cfg
+        .statusbar_format
+        .as_ref()
+        .map(|format| ui::statusbar::Format::parse(format))
+        .unwrap_or_default();
+
+    let mut screens = {
+        let status = status.clone();
+        let statusbar_events = event_manager.clone();
+        ui::screens::ScreenManager::new(
+            move || {
+                ui::statusbar::StatusBar::new(
+                    status.clone(),
+                    statusbar_format.clone(),
+                    statusbar_events.clone(),
+                )
+            },
+            event_manager.clone(),
+        )
     };
 
-    let searchscreen = cursive.active_screen();
-    let search = ui::search::SearchView::new(spotify.clone(), queue.clone());
-    cursive.add_fullscreen_layer(
-        LinearLayout::new(cursive::direction::Orientation::Vertical)
-            .child(search.view)
-            .child(make_statusbar()),
+    // the screens in the order of their tabs, F1 shows the first one
+    let playlist_view = ui::playlist::PlaylistView::new(
+        spotify.clone(),
+        queue.clone(),
+        event_manager.clone(),
+        cfg.play_mode,
     );
+    let playlist_screen = screens.add(&mut cursive, "Playlists", playlist_view.view);
 
-    let queuescreen = cursive.add_active_screen();
-    let queue_view = ui::queue::QueueView::new(queue.clone(), event_manager.clone());
-    cursive.add_fullscreen_layer(
-        LinearLayout::new(cursive::direction::Orientation::Vertical)
-            .child(queue_view.view)
-            .child(make_statusbar()),
-    );
+    let queue_view =
+        ui::queue::QueueView::new(queue.clone(), spotify.clone(), event_manager.clone());
+    let queue_screen = screens.add(&mut cursive, "Queue", queue_view.view);
+
+    let search = ui::search::SearchView::new(spotify.clone(), queue.clone(), event_manager.clone());
+    screens.add(&mut cursive, "Search", search.view);
 
-    let logscreen = cursive.add_active_screen();
     let logview_scroller = ScrollView::new(logview).scroll_strategy(ScrollStrategy::StickToBottom);
     let logpanel = Panel::new(logview_scroller).title("Log");
-    cursive.add_fullscreen_layer(
-        LinearLayout::new(cursive::direction::Orientation::Vertical)
-            .child(logpanel)
-            .child(make_statusbar()),
-    );
+    screens.add(&mut cursive, "Log", logpanel);
+
+    let albums_view =
+        ui::albums::AlbumsView::new(spotify.clone(), queue.clone(), event_manager.clone());
+    screens.add(&mut cursive, "Albums", albums_view.view);
+
+    let artists_view =
+        ui::artists::ArtistsView::new(spotify.clone(), queue.clone(), event_manager.clone());
+    screens.add(&mut cursive, "Artists", artists_view.view);
 
-    let playlist_screen = cursive.add_active_screen();
-    let playlist_view =
-        ui::playlist::PlaylistView::new(spotify.clone(), queue.clone(), event_manager.clone());
-    cursive.add_fullscreen_layer(
-        LinearLayout::new(cursive::direction::Orientation::Vertical)
-            .child(playlist_view.view)
-            .child(make_statusbar()),
+    let now_playing_view = ui::nowplaying::NowPlayingView::new(
+        spotify.clone(),
+        queue.clone(),
+        status.clone(),
+        cfg.cover,
     );
+    screens.add(&mut cursive, "Now playing", now_playing_view.view);
 
-    let screen_idx = Arc::new(AtomicUsize::new(0));
+    let info_view = ui::info::InfoView::new(file_cache.clone());
+    screens.add(&mut cursive, "Info", info_view.view);
 
-    {
-        let screen_idx = screen_idx.clone();
-        cursive.add_global_callback(Key::F4, move |s| {
-            s.set_screen(logscreen);
-            screen_idx.store(2, Ordering::Relaxed);
+    screens.show(&mut cursive, 0);
+    let screens = Rc::new(RefCell::new(screens));
+
+    for index in 0..screens.borrow().len() {
+        let screens = screens.clone();
+        cursive.add_global_callback(Key::from_f(index as u8 + 1), move |s| {
+            screens.borrow_mut().show(s, index);
         });
     }
 
     {
-        let event_manager = event_manager.clone();
-        let screen_idx = screen_idx.clone();
-        cursive.add_global_callback(Key::F2, move |s| {
-            s.set_screen(queuescreen);
-            screen_idx.store(1, Ordering::Relaxed);
-            event_manager.clone().send(Event::QueueUpdate);
+        let screens = screens.clone();
+        cursive.add_global_callback(Key::Tab, move |s| {
+            screens.borrow_mut().next(s);
         });
     }
 
     {
-        let screen_idx = screen_idx.clone();
-        cursive.add_global_callback(Key::F3, move |s| {
-            screen_idx.store(2, Ordering::Relaxed);
-            s.set_screen(searchscreen);
+        let screens = screens.clone();
+        cursive.add_global_callback(CursiveEvent::Shift(Key::Tab), move |s| {
+            screens.borrow_mut().previous(s);
         });
     }
 
+    // <alt-left> goes back to the previous screen or view, <alt-right>
+    // forward again. Keys that text fields ignore, like <backspace> in an
+    // empty one, would navigate while typing.
     {
-        let screen_idx = screen_idx.clone();
-        cursive.add_global_callback(Key::F1, move |s| {
-            screen_idx.store(0, Ordering::Relaxed);
-            s.set_screen(playlist_screen);
+        let screens = screens.clone();
+        cursive.add_global_callback(CursiveEvent::Alt(Key::Left), move |s| {
+            screens.borrow_mut().back(s);
         });
     }
 
     {
-        let screen_idx = screen_idx.clone();
-        let screen_order = vec![playlist_screen, queuescreen, searchscreen];
-        let event_manager = event_manager.clone();
-        cursive.add_global_callback(Key::Tab, move |s| {
-            let idx = screen_idx.fetch_add(1, Ordering::Relaxed);
-            s.set_screen(screen_order[(idx + 1) % screen_order.len()]);
-            event_manager.clone().send(Event::QueueUpdate);
+        let screens = screens.clone();
+        cursive.add_global_callback(CursiveEvent::Alt(Key::Right), move |s| {
+            screens.borrow_mut().forward(s);
         });
     }
 
@@ -218,7 +294,14 @@ fn main() {
 
     cursive.set_fps(fps);
 
+    let notifier = notification::Notifier::new(&cfg.notifications);
+    let mut hooks = hooks::Hooks::new(cfg.hooks);
+    let mut scrobbler = scrobbler::Scrobbler::new(cfg.scrobbler);
+
     let mut current_track: Option<FullTrack> = None;
+    let mut preloaded: Option<String> = None;
+    let crossfade_ms = cfg.crossfade_ms.unwrap_or(0);
+    let mut crossfading = false;
     let mut ticks = 0;
     let mut increment_ticks = false;
 
@@ -249,15 +332,41 @@ fn main() {
     while cursive.is_running() {
         cursive.step();
         if ticks % update_tick == 0 {
+            if current_track.is_some() {
+                status.lock().unwrap().set_elapsed(ticks * 1000 / fps);
+            }
+            if let Some(ref mut scrobbler) = scrobbler {
+                scrobbler.update();
+            }
+
             if let Some(ref current_track) = current_track {
-                counter.set((ticks * 1000 / fps * 1_00 / current_track.duration_ms) as usize);
-                playback_info.set_content(format!(
-                    "{}:{:02} / {}:{:02}",
-                    (ticks / fps) / 60,
-                    (ticks / fps) % 60,
-                    (current_track.duration_ms / 1000) / 60,
-                    (current_track.duration_ms / 1000) % 60,
-                ));
+                let elapsed = ticks * 1000 / fps;
+                let remaining = current_track.duration_ms.saturating_sub(elapsed);
+
+                // the next track is loaded shortly before the current one
+                // ends, and again if the queue changed in the meantime. A
+                // track that is too short to fade in and out is left alone,
+                // as the player that fades it in would be taken.
+                if remaining <= PRELOAD_MS && elapsed >= crossfade_ms {
+                    if let Some(next) = queue.lock().unwrap().iter().next() {
+                        if preloaded.as_ref() != Some(&next.id) {
+                            spotify.preload(next);
+                            preloaded = Some(next.id.clone());
+                        }
+                    }
+                }
+
+                // the next track starts before the current one ends, unless
+                // the current one is too short to fade in and out
+                if crossfade_ms > 0
+                    && !crossfading
+                    && remaining <= crossfade_ms
+                    && elapsed >= crossfade_ms
+                    && !queue.lock().unwrap().is_empty()
+                {
+                    spotify.crossfade();
+                    crossfading = true;
+                }
             }
         }
         if increment_ticks {
@@ -266,9 +375,23 @@ fn main() {
 
         for event in event_manager.msg_iter() {
             trace!("event received {}", event);
+            status.lock().unwrap().handle(&event);
+            if let Some(ref mut scrobbler) = scrobbler {
+                scrobbler.handle(&event);
+            }
             match event {
                 Event::QueueUpdate => {
+                    // the queue can only be reached on its own screen
+                    let active_screen = cursive.active_screen();
+                    cursive.set_screen(queue_screen);
                     ui::queue::QueueView::redraw(&mut cursive, queue.clone());
+                    cursive.set_screen(active_screen);
+                    // the preloaded track isn't played next anymore
+                    let next = queue.lock().unwrap().iter().next().map(|t| t.id.clone());
+                    if preloaded.is_some() && preloaded != next {
+                        spotify.cancel_preload();
+                        preloaded = None;
+                    }
                     if spotify.is_stopped() && !queue.lock().unwrap().is_empty() {
                         event_manager.send(Event::CheckQueue);
                     }
@@ -286,21 +409,24 @@ fn main() {
                 }
                 Event::SongChange(track) => {
                     trace!("New track: {}", track.name);
-                    // statusbar.set_content(format!("{}", track.name));
-                    track_info.set_content(format!(
-                        "{} - {}",
-                        track
-                            .artists
-                            .iter()
-                            .map(|a| a.name.clone())
-                            .collect::<Vec<String>>()
-                            .join(", "),
-                        track.name,
-                    ));
+                    show_playing(&mut cursive, &spotify, playlist_screen, Some(&track));
+                    if let Some(ref notifier) = notifier {
+                        notifier.song_change(&track);
+                    }
+                    hooks.song_change(&track);
+                    // the audio files are written once they are complete, so the
+                    // file of the last track is included now
+                    if let Some(ref file_cache) = file_cache {
+                        let file_cache = file_cache.clone();
+                        thread::spawn(move || file_cache.evict());
+                    }
                     current_track = Some(track);
+                    preloaded = None;
+                    crossfading = false;
                     ticks = 0;
                 }
                 Event::PlayState(state) => {
+                    hooks.play_state(state, current_track.as_ref());
                     match &state {
                         spotify::PlayerState::Playing => {
                             increment_ticks = true;
@@ -309,7 +435,9 @@ fn main() {
                             increment_ticks = false;
                         }
                         spotify::PlayerState::Stopped => {
+                            show_playing(&mut cursive, &spotify, playlist_screen, None);
                             current_track = None;
+                            preloaded = None;
                             ticks = 0;
                             increment_ticks = false;
                         }
@@ -328,10 +456,67 @@ fn main() {
                 Event::CheckQueue => {
                     spotify.check_queue();
                 }
+                Event::PlaylistsUpdate => {
+                    let active_screen = cursive.active_screen();
+                    cursive.set_screen(playlist_screen);
+                    ui::playlist::PlaylistView::reload_playlists(&mut cursive, &spotify);
+                    cursive.set_screen(active_screen);
+                }
+                Event::PlaylistsLoaded(playlists) => {
+                    let active_screen = cursive.active_screen();
+                    cursive.set_screen(playlist_screen);
+                    ui::playlist::PlaylistView::set_playlists(&mut cursive, &spotify, playlists);
+                    cursive.set_screen(active_screen);
+                }
+                Event::PlaylistUpdate(id, snapshot_id) => {
+                    // the playlist tree can only be reached on its own screen
+                    let active_screen = cursive.active_screen();
+                    cursive.set_screen(playlist_screen);
+                    ui::playlist::PlaylistView::reload_playlist(
+                        &mut cursive,
+                        &spotify,
+                        &id,
+                        &snapshot_id,
+                    );
+                    cursive.set_screen(active_screen);
+                }
+                Event::ShowAlbum(id) => {
+                    if let Some(album) = ui::album::AlbumView::open(
+                        spotify.clone(),
+                        queue.clone(),
+                        event_manager.clone(),
+                        &id,
+                    ) {
+                        screens.borrow_mut().push(&mut cursive, album.view);
+                    }
+                }
+                Event::ShowArtist(id) => {
+                    if let Some(artist) = ui::artist::ArtistView::open(
+                        spotify.clone(),
+                        queue.clone(),
+                        event_manager.clone(),
+                        &id,
+                    ) {
+                        screens.borrow_mut().push(&mut cursive, artist.view);
+                    }
+                }
+                Event::ShowScreen(index) => {
+                    screens.borrow_mut().show(&mut cursive, index);
+                }
+                Event::NavigateBack => {
+                    screens.borrow_mut().close_page(&mut cursive);
+                }
                 Event::SeekTo(ms) => {
                     ticks = ms * fps / 1000;
                     spotify.seek_ms(ms);
                 }
+                Event::SeekToPercent(percent) => {
+                    if let Some(ref current_track) = current_track {
+                        let ms = current_track.duration_ms * percent as u32 / 100;
+                        ticks = ms * fps / 1000;
+                        spotify.seek_ms(ms);
+                    }
+                }
                 Event::SeekForward(ms) => {
                     if let Some(ref current_track) = current_track {
                         let ms = std::cmp::min(ticks * 1000 / fps + ms, current_track.duration_ms);
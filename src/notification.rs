@@ -0,0 +1,121 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use log::error;
+use notify_rust::Notification;
+
+use rspotify::spotify::model::track::FullTrack;
+
+use crate::config::NotificationConfig;
+use crate::ui::tracklist::{format_duration, join_artists};
+
+const DEFAULT_SUMMARY: &str = "{title}";
+const DEFAULT_BODY: &str = "{artist} - {album}";
+
+/// Shows a desktop notification through the D-Bus notification service
+/// whenever the track changes.
+pub struct Notifier {
+    summary: String,
+    body: String,
+
+    // id of the last notification, which is replaced by the next one so they
+    // don't pile up
+    last_id: Arc<Mutex<Option<u32>>>,
+}
+
+impl Notifier {
+    /// Returns `None` unless notifications are enabled.
+    pub fn new(cfg: &Option<NotificationConfig>) -> Option<Notifier> {
+        let cfg = cfg.as_ref().filter(|cfg| cfg.enabled)?;
+        Some(Notifier {
+            summary: cfg
+                .summary
+                .clone()
+                .unwrap_or_else(|| DEFAULT_SUMMARY.to_string()),
+            body: cfg.body.clone().unwrap_or_else(|| DEFAULT_BODY.to_string()),
+            last_id: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    pub fn song_change(&self, track: &FullTrack) {
+        let summary = fill(&self.summary, track);
+        let body = fill(&self.body, track);
+        let last_id = self.last_id.clone();
+
+        // the notification service may take a while to answer, or time out
+        // if there is none
+        thread::spawn(move || {
+            let mut notification = Notification::new();
+            notification.appname("ncspot").summary(&summary).body(&body);
+            if let Some(id) = *last_id.lock().unwrap() {
+                notification.id(id);
+            }
+            match notification.show() {
+                Ok(handle) => *last_id.lock().unwrap() = Some(handle.id()),
+                Err(e) => error!("could not show notification: {}", e),
+            }
+        });
+    }
+}
+
+// replaces the placeholders of a template with the details of the track.
+// The template is scanned once, so placeholders within the details are kept
+// as they are.
+fn fill(template: &str, track: &FullTrack) -> String {
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let value = rest[start..].find('}').and_then(|end| {
+            let value = match &rest[start + 1..start + end] {
+                "title" => track.name.clone(),
+                "artist" => join_artists(track),
+                "album" => track.album.name.clone(),
+                "duration" => format_duration(track.duration_ms),
+                _ => return None,
+            };
+            Some((value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                filled.push_str(&rest[..start]);
+                filled.push_str(&value);
+                rest = &rest[start + end + 1..];
+            }
+            None => {
+                filled.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn fill_replaces_placeholders() {
+        let track = fixtures::track();
+        assert_eq!(
+            fill("{title} by {artist} on {album} ({duration})", &track),
+            "The Title by First Artist, Second Artist on The Album (3:20)"
+        );
+    }
+
+    #[test]
+    fn fill_keeps_other_braces() {
+        let track = fixtures::track();
+        assert_eq!(fill("{unknown} {title", &track), "{unknown} {title");
+        assert_eq!(fill("{{title}}", &track), "{The Title}");
+    }
+
+    #[test]
+    fn fill_keeps_placeholders_in_details() {
+        let mut track = fixtures::track();
+        track.name = "{album}".to_string();
+        assert_eq!(fill("{title} - {album}", &track), "{album} - The Album");
+    }
+}
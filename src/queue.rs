@@ -32,6 +32,12 @@ impl Queue {
         self.send_event();
     }
 
+    pub fn replace(&mut self, tracks: Vec<FullTrack>) {
+        debug!("Replacing queue with {} tracks", tracks.len());
+        self.queue = tracks.into_iter().collect();
+        self.send_event();
+    }
+
     pub fn remove(&mut self, index: usize) -> Option<FullTrack> {
         match self.queue.remove(index) {
             Some(track) => {
@@ -43,6 +49,21 @@ impl Queue {
         }
     }
 
+    /// Removes the tracks at the given indices at once.
+    pub fn remove_all(&mut self, indices: &[usize]) -> Vec<FullTrack> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable_by(|a, b| b.cmp(a));
+        indices.dedup();
+
+        let removed: Vec<FullTrack> = indices
+            .into_iter()
+            .filter_map(|index| self.queue.remove(index))
+            .collect();
+        debug!("Removed {} tracks from queue", removed.len());
+        self.send_event();
+        removed
+    }
+
     pub fn enqueue(&mut self, track: FullTrack) {
         debug!("Queued: {}", &track.name);
         self.queue.push_back(track);
@@ -0,0 +1,520 @@
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use failure::Error;
+use log::{error, info};
+use serde_derive::{Deserialize, Serialize};
+
+use rspotify::spotify::model::track::FullTrack;
+
+use crate::config::{self, ScrobblerConfig};
+use crate::events::Event;
+use crate::spotify::PlayerState;
+use crate::ui::tracklist::join_artists;
+
+const DEFAULT_URL: &str = "https://api.listenbrainz.org";
+
+// tracks need to be this long to be scrobbled, and played for half their
+// length or this long, whichever is shorter
+const MIN_DURATION_MS: u32 = 30_000;
+const MAX_PLAYED_MS: u32 = 4 * 60 * 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TrackMetadata {
+    artist_name: String,
+    track_name: String,
+    release_name: String,
+    additional_info: AdditionalInfo,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct AdditionalInfo {
+    spotify_id: String,
+    duration_ms: u32,
+    tracknumber: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Listen {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    listened_at: Option<u64>,
+    track_metadata: TrackMetadata,
+}
+
+#[derive(Serialize, Debug)]
+struct Submission<'a> {
+    listen_type: &'static str,
+    payload: [&'a Listen; 1],
+}
+
+enum Message {
+    NowPlaying(Listen),
+    Scrobble(Listen),
+}
+
+// the track that is playing and how long it was played so far
+struct Play {
+    listen: Listen,
+    // how long the track has to be played to be scrobbled
+    needed_ms: u32,
+    played: Duration,
+    // set while the track is playing
+    since: Option<Instant>,
+    scrobbled: bool,
+}
+
+impl Play {
+    fn played_ms(&self) -> u32 {
+        let played = match self.since {
+            Some(since) => self.played + since.elapsed(),
+            None => self.played,
+        };
+        (played.as_secs() * 1000 + u64::from(played.subsec_millis())) as u32
+    }
+
+    fn pause(&mut self) {
+        if let Some(since) = self.since.take() {
+            self.played += since.elapsed();
+        }
+    }
+}
+
+/// Submits the tracks that are played to a ListenBrainz compatible service:
+/// a track is announced as playing now once it starts, and scrobbled once it
+/// was played for half its length or four minutes. Scrobbles that can't be
+/// submitted are kept on disk and submitted again later.
+pub struct Scrobbler {
+    tx: Sender<Message>,
+    play: Option<Play>,
+}
+
+impl Scrobbler {
+    pub fn new(cfg: Option<ScrobblerConfig>) -> Option<Scrobbler> {
+        let cfg = cfg?;
+        let (tx, rx) = unbounded();
+        let mut worker = Worker {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .ok()?,
+            url: format!(
+                "{}/1/submit-listens",
+                cfg.url
+                    .as_ref()
+                    .map_or(DEFAULT_URL, String::as_str)
+                    .trim_end_matches('/')
+            ),
+            token: cfg.token,
+            queue: config::cache_dir().map(|dir| dir.join("scrobbles")),
+        };
+        thread::spawn(move || worker.run(rx));
+
+        Some(Scrobbler { tx, play: None })
+    }
+
+    /// Follows the events changing the track or the state of the player.
+    pub fn handle(&mut self, event: &Event) {
+        match *event {
+            Event::SongChange(ref track) => {
+                self.play = None;
+                let needed_ms = match needed_ms(track.duration_ms) {
+                    Some(needed_ms) => needed_ms,
+                    None => return,
+                };
+                let listen = listen(track);
+                self.send(Message::NowPlaying(listen.clone()));
+                self.play = Some(Play {
+                    listen,
+                    needed_ms,
+                    played: Duration::from_secs(0),
+                    since: Some(Instant::now()),
+                    scrobbled: false,
+                });
+            }
+            Event::PlayState(state) => {
+                if let Some(ref mut play) = self.play {
+                    match state {
+                        PlayerState::Playing if play.since.is_none() => {
+                            play.since = Some(Instant::now())
+                        }
+                        PlayerState::Paused | PlayerState::Stopped => play.pause(),
+                        _ => (),
+                    }
+                }
+                self.update();
+                if state == PlayerState::Stopped {
+                    self.play = None;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Scrobbles the track once it was played long enough, to be called
+    /// regularly.
+    pub fn update(&mut self) {
+        let listen = match self.play {
+            Some(ref mut play) if !play.scrobbled => {
+                if play.played_ms() < play.needed_ms {
+                    return;
+                }
+                play.scrobbled = true;
+                play.listen.clone()
+            }
+            _ => return,
+        };
+        self.send(Message::Scrobble(listen));
+    }
+
+    fn send(&self, message: Message) {
+        if self.tx.send(message).is_err() {
+            error!("the scrobbler stopped");
+        }
+    }
+}
+
+// returns how long a track has to be played to be scrobbled, or `None` if it
+// is too short to be scrobbled at all
+fn needed_ms(duration_ms: u32) -> Option<u32> {
+    if duration_ms < MIN_DURATION_MS {
+        None
+    } else {
+        Some((duration_ms / 2).min(MAX_PLAYED_MS))
+    }
+}
+
+fn listen(track: &FullTrack) -> Listen {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Listen {
+        listened_at: Some(now),
+        track_metadata: TrackMetadata {
+            artist_name: join_artists(track),
+            track_name: track.name.clone(),
+            release_name: track.album.name.clone(),
+            additional_info: AdditionalInfo {
+                spotify_id: track.uri.clone(),
+                duration_ms: track.duration_ms,
+                tracknumber: track.track_number,
+            },
+        },
+    }
+}
+
+// submits the listens in the background
+struct Worker {
+    client: reqwest::Client,
+    url: String,
+    token: String,
+    // file of the scrobbles that couldn't be submitted yet, one per line
+    queue: Option<PathBuf>,
+}
+
+impl Worker {
+    fn run(&mut self, rx: Receiver<Message>) {
+        self.submit_queued();
+        for message in rx.iter() {
+            match message {
+                Message::NowPlaying(mut listen) => {
+                    listen.listened_at = None;
+                    if let Err(e) = self.submit("playing_now", &listen) {
+                        error!("could not submit playing track: {}", e);
+                    }
+                }
+                Message::Scrobble(listen) => {
+                    // older scrobbles go first, so they stay in order
+                    if !self.submit_queued() {
+                        self.enqueue(&listen);
+                    } else if let Err(e) = self.submit("single", &listen) {
+                        error!("could not scrobble, keeping it for later: {}", e);
+                        self.enqueue(&listen);
+                    }
+                }
+            }
+        }
+    }
+
+    fn submit(&self, listen_type: &'static str, listen: &Listen) -> Result<(), Error> {
+        let submission = Submission {
+            listen_type,
+            payload: [listen],
+        };
+        self.client
+            .post(&self.url)
+            .header("Authorization", format!("Token {}", self.token))
+            .json(&submission)
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    fn enqueue(&self, listen: &Listen) {
+        let path = match self.queue {
+            Some(ref path) => path,
+            None => return,
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(path))
+            .and_then(|mut file| {
+                let line = serde_json::to_string(listen).unwrap_or_default();
+                writeln!(file, "{}", line)
+            });
+        if let Err(e) = result {
+            error!("could not keep scrobble in {}: {}", path.display(), e);
+        }
+    }
+
+    // submits the queued scrobbles and returns whether all of them were
+    // submitted, keeping the rest in the queue
+    fn submit_queued(&self) -> bool {
+        let path = match self.queue {
+            Some(ref path) => path,
+            None => return true,
+        };
+        let listens: Vec<Listen> = match File::open(path) {
+            Ok(file) => BufReader::new(file)
+                .lines()
+                .filter_map(|line| serde_json::from_str(&line.ok()?).ok())
+                .collect(),
+            Err(_) => return true,
+        };
+
+        let mut submitted = 0;
+        for listen in &listens {
+            if let Err(e) = self.submit("single", listen) {
+                error!("could not submit queued scrobbles: {}", e);
+                break;
+            }
+            submitted += 1;
+        }
+        if submitted > 0 {
+            info!("submitted {} queued scrobbles", submitted);
+        }
+
+        let rest = &listens[submitted..];
+        let result = if rest.is_empty() {
+            fs::remove_file(path)
+        } else {
+            let lines: Vec<String> = rest
+                .iter()
+                .filter_map(|listen| serde_json::to_string(listen).ok())
+                .collect();
+            fs::write(path, lines.join("\n") + "\n")
+        };
+        if let Err(e) = result {
+            error!("could not update {}: {}", path.display(), e);
+        }
+        rest.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use serde_json::Value;
+    use std::env;
+    use std::net::TcpListener;
+    use std::process;
+
+    // answers one request per connection with the given statuses, and
+    // returns the url of the server along with the authorization and the
+    // body of every request
+    fn server(statuses: Vec<u16>) -> (String, Receiver<(String, Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = unbounded();
+        thread::spawn(move || {
+            for (stream, status) in listener.incoming().zip(statuses) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let (mut authorization, mut length) = (String::new(), 0);
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let (name, value) = match line.find(": ") {
+                        Some(i) => (line[..i].to_lowercase(), &line[i + 2..]),
+                        None => continue,
+                    };
+                    match name.as_str() {
+                        "authorization" => authorization = value.to_string(),
+                        "content-length" => length = value.parse().unwrap(),
+                        _ => (),
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                tx.send((authorization, serde_json::from_slice(&body).unwrap()))
+                    .unwrap();
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                )
+                .unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    fn worker(url: &str, name: &str) -> Worker {
+        let queue = env::temp_dir().join(format!("ncspot-scrobbles-{}-{}", name, process::id()));
+        Worker {
+            client: reqwest::Client::new(),
+            url: format!("{}/1/submit-listens", url),
+            token: "secret".to_string(),
+            queue: Some(queue),
+        }
+    }
+
+    // runs the worker until it has handled the messages
+    fn run(worker: &mut Worker, messages: Vec<Message>) {
+        let (tx, rx) = unbounded();
+        for message in messages {
+            tx.send(message).unwrap();
+        }
+        drop(tx);
+        worker.run(rx);
+    }
+
+    fn track_name(body: &Value) -> &str {
+        body["payload"][0]["track_metadata"]["track_name"]
+            .as_str()
+            .unwrap()
+    }
+
+    #[test]
+    fn short_tracks_are_not_scrobbled() {
+        assert_eq!(needed_ms(29_999), None);
+        assert_eq!(needed_ms(30_000), Some(15_000));
+    }
+
+    #[test]
+    fn tracks_are_scrobbled_after_half_or_four_minutes() {
+        assert_eq!(needed_ms(200_000), Some(100_000));
+        assert_eq!(needed_ms(480_000), Some(240_000));
+        assert_eq!(needed_ms(600_000), Some(240_000));
+    }
+
+    #[test]
+    fn listen_has_track_details() {
+        let listen = listen(&fixtures::track());
+        assert!(listen.listened_at.is_some());
+
+        let metadata = listen.track_metadata;
+        assert_eq!(metadata.artist_name, "First Artist, Second Artist");
+        assert_eq!(metadata.track_name, "The Title");
+        assert_eq!(metadata.release_name, "The Album");
+        assert_eq!(metadata.additional_info.spotify_id, "spotify:track:track1");
+        assert_eq!(metadata.additional_info.duration_ms, 200_000);
+        assert_eq!(metadata.additional_info.tracknumber, 3);
+    }
+
+    #[test]
+    fn track_is_scrobbled_once_played_long_enough() {
+        let (tx, rx) = unbounded();
+        let mut scrobbler = Scrobbler { tx, play: None };
+
+        scrobbler.handle(&Event::SongChange(fixtures::track()));
+        match rx.try_recv() {
+            Ok(Message::NowPlaying(listen)) => {
+                assert_eq!(listen.track_metadata.track_name, "The Title")
+            }
+            _ => panic!("the track wasn't announced"),
+        }
+
+        scrobbler.update();
+        assert!(rx.try_recv().is_err());
+
+        // paused right before the threshold
+        scrobbler.handle(&Event::PlayState(PlayerState::Paused));
+        if let Some(ref mut play) = scrobbler.play {
+            play.played = Duration::from_millis(99_999);
+        }
+        scrobbler.update();
+        assert!(rx.try_recv().is_err());
+
+        if let Some(ref mut play) = scrobbler.play {
+            play.played = Duration::from_millis(100_000);
+        }
+        scrobbler.update();
+        match rx.try_recv() {
+            Ok(Message::Scrobble(listen)) => {
+                assert_eq!(listen.track_metadata.track_name, "The Title")
+            }
+            _ => panic!("the track wasn't scrobbled"),
+        }
+
+        // a track is only scrobbled once
+        scrobbler.update();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn scrobble_is_submitted_with_token() {
+        let (url, requests) = server(vec![200]);
+        let mut worker = worker(&url, "submit");
+        run(
+            &mut worker,
+            vec![Message::Scrobble(listen(&fixtures::track()))],
+        );
+
+        let (authorization, body) = requests.recv().unwrap();
+        assert_eq!(authorization, "Token secret");
+        assert_eq!(body["listen_type"], "single");
+        assert_eq!(track_name(&body), "The Title");
+        assert!(body["payload"][0]["listened_at"].is_u64());
+        assert!(!worker.queue.unwrap().exists());
+    }
+
+    #[test]
+    fn playing_track_is_submitted_without_time() {
+        let (url, requests) = server(vec![200]);
+        let mut worker = worker(&url, "playing");
+        run(
+            &mut worker,
+            vec![Message::NowPlaying(listen(&fixtures::track()))],
+        );
+
+        let (_, body) = requests.recv().unwrap();
+        assert_eq!(body["listen_type"], "playing_now");
+        assert_eq!(track_name(&body), "The Title");
+        assert!(body["payload"][0].get("listened_at").is_none());
+    }
+
+    #[test]
+    fn failed_scrobble_is_queued_and_replayed() {
+        let (url, requests) = server(vec![500, 200, 200]);
+        let mut worker = worker(&url, "queue");
+        let queue = worker.queue.clone().unwrap();
+        let _ = fs::remove_file(&queue);
+
+        let first = listen(&fixtures::track());
+        run(&mut worker, vec![Message::Scrobble(first)]);
+        assert_eq!(track_name(&requests.recv().unwrap().1), "The Title");
+        let queued = fs::read_to_string(&queue).unwrap();
+        assert_eq!(queued.lines().count(), 1);
+        assert!(queued.contains("The Title"));
+
+        // the queued scrobble goes first, and is dropped once it is sent
+        let mut track = fixtures::track();
+        track.name = "The Next Title".to_string();
+        run(&mut worker, vec![Message::Scrobble(listen(&track))]);
+        assert_eq!(track_name(&requests.recv().unwrap().1), "The Title");
+        assert_eq!(track_name(&requests.recv().unwrap().1), "The Next Title");
+        assert!(!queue.exists());
+    }
+}
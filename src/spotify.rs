@@ -1,4 +1,5 @@
 use librespot::core::authentication::Credentials;
+use librespot::core::cache::Cache;
 use librespot::core::config::SessionConfig;
 use librespot::core::keymaster::get_token;
 use librespot::core::keymaster::Token;
@@ -6,15 +7,24 @@ use librespot::core::session::Session;
 use librespot::core::spotify_id::SpotifyId;
 use librespot::playback::config::PlayerConfig;
 
-use librespot::playback::audio_backend;
+use librespot::playback::audio_backend::{self, Sink};
 use librespot::playback::config::Bitrate;
 use librespot::playback::player::Player;
 
 use rspotify::spotify::client::Spotify as SpotifyAPI;
+use rspotify::spotify::model::album::{FullAlbum, SimplifiedAlbum};
+use rspotify::spotify::model::artist::FullArtist;
+use rspotify::spotify::model::playlist::{PlaylistTrack, SimplifiedPlaylist};
 use rspotify::spotify::model::search::SearchTracks;
+use rspotify::spotify::model::track::FullTrack;
+use rspotify::spotify::senum::AlbumType;
 
 use failure::Error;
 
+use serde_json::json;
+use serde_json::map::Map;
+use serde_json::Value;
+
 use futures;
 use futures::sync::mpsc;
 use futures::sync::oneshot;
@@ -23,15 +33,19 @@ use futures::Future;
 use futures::Stream;
 use tokio_core::reactor::Core;
 
-use log::{debug, info, trace};
+use log::{debug, error, info, trace};
 
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::RwLock;
 use std::thread;
 
+use crate::cache::MetadataCache;
+use crate::crossfade::Mixer;
 use crate::events::{Event, EventManager};
+use crate::filecache::FileCache;
 use crate::queue::Queue;
+use crate::wav::WavSink;
 
 enum WorkerCommand {
     Load(SpotifyId),
@@ -40,8 +54,12 @@ enum WorkerCommand {
     Stop,
     CheckQueue,
     Seek(u32),
+    Preload(SpotifyId),
+    CancelPreload,
+    Crossfade,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum PlayerState {
     Playing,
     Paused,
@@ -50,16 +68,27 @@ pub enum PlayerState {
 
 pub struct Spotify {
     pub state: RwLock<PlayerState>,
+    // id of the track that is playing or paused
+    current_track: RwLock<Option<String>>,
     pub api: SpotifyAPI,
+    pub user: String,
     channel: mpsc::UnboundedSender<WorkerCommand>,
     events: EventManager,
+    cache: MetadataCache,
 }
 
 struct Worker {
     events: EventManager,
     commands: mpsc::UnboundedReceiver<WorkerCommand>,
-    player: Player,
+    // two players take turns, one plays while the other one preloads the
+    // next track or fades out the previous one
+    players: Vec<Player>,
+    active: usize,
     play_task: Box<futures::Future<Item = (), Error = oneshot::Canceled>>,
+    // the track loaded on the other player, along with the end of it
+    preload: Option<(SpotifyId, oneshot::Receiver<()>)>,
+    // whether the other player fades out the previous track
+    fading: bool,
     stopped: bool,
     queue: Arc<Mutex<Queue>>,
 }
@@ -68,18 +97,93 @@ impl Worker {
     fn new(
         events: EventManager,
         commands: mpsc::UnboundedReceiver<WorkerCommand>,
-        player: Player,
+        players: Vec<Player>,
         queue: Arc<Mutex<Queue>>,
     ) -> Worker {
         Worker {
             events: events,
             commands: commands,
-            player: player,
+            players: players,
+            active: 0,
             play_task: Box::new(futures::empty()),
+            preload: None,
+            fading: false,
             stopped: true,
             queue: queue,
         }
     }
+
+    fn player(&self) -> &Player {
+        &self.players[self.active]
+    }
+
+    fn other(&self) -> &Player {
+        &self.players[1 - self.active]
+    }
+
+    // plays the next track of the queue. It starts on the other player if
+    // that has preloaded it, or if `crossfade` is set so the current track
+    // keeps playing while it fades out. Returns whether there was a next
+    // track.
+    fn play_next(&mut self, crossfade: bool) -> bool {
+        let track = match self.queue.lock().unwrap().dequeue() {
+            Some(track) => track,
+            None => return false,
+        };
+        debug!("next track in queue: {}", track.name);
+        let trackid = SpotifyId::from_base62(&track.id).expect("could not load track");
+        match self.preload.take() {
+            Some((id, end_of_track)) if id == trackid => {
+                self.active = 1 - self.active;
+                self.play_task = Box::new(end_of_track);
+            }
+            preload => {
+                if preload.is_some() {
+                    self.other().stop();
+                }
+                if crossfade {
+                    self.active = 1 - self.active;
+                }
+                self.play_task = Box::new(self.player().load(trackid, false, 0));
+            }
+        }
+        self.player().play();
+        self.fading = crossfade;
+        self.stopped = false;
+
+        self.events.send(Event::SongChange(track));
+        self.events.send(Event::PlayState(PlayerState::Playing));
+        true
+    }
+
+    // stops the other player if it fades out the previous track
+    fn stop_fading(&mut self) {
+        if self.fading {
+            self.other().stop();
+            self.fading = false;
+        }
+    }
+
+    // loads a track paused on the other player, so it only has to be started
+    // once the current track ends. Preloading another track replaces the
+    // previous one.
+    fn preload(&mut self, id: SpotifyId) {
+        if self.preload.as_ref().map(|(preloaded, _)| *preloaded) == Some(id) {
+            return;
+        }
+        info!("preloading track: {:?}", id);
+        let end_of_track = self.other().load(id, false, 0);
+        self.preload = Some((id, end_of_track));
+        // loading stops whatever the other player played
+        self.fading = false;
+    }
+
+    fn cancel_preload(&mut self) {
+        if self.preload.take().is_some() {
+            debug!("dropping preloaded track");
+            self.other().stop();
+        }
+    }
 }
 
 impl futures::Future for Worker {
@@ -96,32 +200,54 @@ impl futures::Future for Worker {
                 debug!("message received!");
                 match cmd {
                     WorkerCommand::Load(track) => {
-                        self.play_task = Box::new(self.player.load(track, false, 0));
+                        // the new track replaces the one fading in, so the
+                        // one fading out ends as well
+                        self.stop_fading();
+                        self.play_task = Box::new(self.player().load(track, false, 0));
                         self.stopped = false;
                         info!("player loading track..");
                     }
                     WorkerCommand::Play => {
-                        self.player.play();
+                        self.stop_fading();
+                        self.player().play();
                         self.stopped = false;
                         self.events.send(Event::PlayState(PlayerState::Playing));
                     }
                     WorkerCommand::Pause => {
-                        self.player.pause();
+                        // a track that is fading out doesn't resume with the
+                        // current one, so it ends here
+                        self.stop_fading();
+                        self.player().pause();
                         self.events.send(Event::PlayState(PlayerState::Paused));
                     }
                     WorkerCommand::Stop => {
-                        self.player.stop();
+                        for player in &self.players {
+                            player.stop();
+                        }
+                        self.preload = None;
+                        self.fading = false;
                         self.stopped = true;
                         self.events.send(Event::PlayState(PlayerState::Stopped));
                     }
                     WorkerCommand::Seek(ms) => {
-                        self.player.seek(ms);
+                        self.stop_fading();
+                        self.player().seek(ms);
                     }
                     WorkerCommand::CheckQueue => {
                         if self.stopped {
                             self.play_task = Box::new(futures::done(Ok(())));
                         }
                     }
+                    WorkerCommand::Preload(track) => {
+                        self.preload(track);
+                    }
+                    WorkerCommand::CancelPreload => {
+                        self.cancel_preload();
+                    }
+                    WorkerCommand::Crossfade => {
+                        // the current track ends on its own, after fading out
+                        self.play_next(true);
+                    }
                 }
             }
             match self.play_task.poll() {
@@ -131,18 +257,7 @@ impl futures::Future for Worker {
 
                     progress = true;
 
-                    let mut queue = self.queue.lock().unwrap();
-                    if let Some(track) = queue.dequeue() {
-                        debug!("next track in queue: {}", track.name);
-                        let trackid =
-                            SpotifyId::from_base62(&track.id).expect("could not load track");
-                        self.play_task = Box::new(self.player.load(trackid, false, 0));
-                        self.player.play();
-                        self.stopped = false;
-
-                        self.events.send(Event::SongChange(track));
-                        self.events.send(Event::PlayState(PlayerState::Playing));
-                    } else {
+                    if !self.play_next(false) {
                         self.events.send(Event::PlayState(PlayerState::Stopped));
                     }
                 }
@@ -169,6 +284,10 @@ impl Spotify {
         password: String,
         client_id: String,
         queue: Arc<Mutex<Queue>>,
+        cache: Option<FileCache>,
+        crossfade_ms: Option<u32>,
+        backend: Option<String>,
+        device: Option<String>,
     ) -> Spotify {
         let session_config = SessionConfig::default();
         let player_config = PlayerConfig {
@@ -192,6 +311,10 @@ impl Spotify {
                     credentials,
                     client_id,
                     queue,
+                    cache,
+                    crossfade_ms,
+                    backend,
+                    device,
                 )
             });
         }
@@ -199,12 +322,16 @@ impl Spotify {
         let token = c.wait().unwrap();
         debug!("token received: {:?}", token);
         let api = SpotifyAPI::default().access_token(&token.access_token);
+        let user = api.current_user().expect("could not load user profile").id;
 
         Spotify {
             state: RwLock::new(PlayerState::Stopped),
+            current_track: RwLock::new(None),
             api: api,
+            user: user,
             channel: tx,
             events: events,
+            cache: MetadataCache::load(),
         }
     }
 
@@ -217,12 +344,19 @@ impl Spotify {
         credentials: Credentials,
         client_id: String,
         queue: Arc<Mutex<Queue>>,
+        cache: Option<FileCache>,
+        crossfade_ms: Option<u32>,
+        backend: Option<String>,
+        device: Option<String>,
     ) {
         let mut core = Core::new().unwrap();
         let handle = core.handle();
 
+        // librespot stores the credentials and the audio files in the cache
+        let cache = cache.map(|cache| Cache::new(cache.dir, cache.audio));
+
         let session = core
-            .run(Session::connect(session_config, credentials, None, handle))
+            .run(Session::connect(session_config, credentials, cache, handle))
             .ok()
             .unwrap();
 
@@ -230,11 +364,28 @@ impl Spotify {
         let token = core.run(get_token(&session, &client_id, &scopes)).unwrap();
         token_channel.send(token).unwrap();
 
-        let backend = audio_backend::find(None).unwrap();
-        let (player, _eventchannel) =
-            Player::new(player_config, session, None, move || (backend)(None));
-
-        let worker = Worker::new(events, commands, player, queue);
+        let backend: fn(Option<String>) -> Box<dyn Sink> = match backend {
+            Some(ref name) if name == "wav" => WavSink::open,
+            Some(name) => audio_backend::find(Some(name.clone())).unwrap_or_else(|| {
+                error!("unknown audio backend {}, using the default one", name);
+                audio_backend::find(None).unwrap()
+            }),
+            None => audio_backend::find(None).unwrap(),
+        };
+        // both players write to a mixer, which writes to the actual sink
+        let mixer = Mixer::new(crossfade_ms.unwrap_or(0), move || (backend)(device));
+        let players = (0..2)
+            .map(|index| {
+                let input = mixer.input(index);
+                let (player, _eventchannel) =
+                    Player::new(player_config.clone(), session.clone(), None, move || {
+                        Box::new(input) as Box<dyn Sink>
+                    });
+                player
+            })
+            .collect();
+
+        let worker = Worker::new(events, commands, players, queue);
         debug!("worker thread ready.");
         core.run(worker).unwrap();
         debug!("worker thread finished.");
@@ -244,6 +395,276 @@ impl Spotify {
         self.api.search_track(query, limit, offset, None)
     }
 
+    pub fn current_user_playlists(&self) -> Result<Vec<SimplifiedPlaylist>, Error> {
+        let mut playlists = Vec::new();
+        loop {
+            let page = self
+                .api
+                .current_user_playlists(50, playlists.len() as u32)?;
+            let done = page.next.is_none();
+            playlists.extend(page.items);
+            if done {
+                break;
+            }
+        }
+        self.cache.set_playlists(&playlists);
+        Ok(playlists)
+    }
+
+    /// Returns the playlists of the user as they were fetched last, which may
+    /// be from an earlier run.
+    pub fn cached_playlists(&self) -> Option<Vec<SimplifiedPlaylist>> {
+        self.cache.playlists()
+    }
+
+    /// Returns the tracks of a playlist, which are only fetched if they
+    /// aren't cached for the current snapshot of the playlist.
+    pub fn playlist_tracks(
+        &self,
+        playlist: &SimplifiedPlaylist,
+    ) -> Result<Vec<PlaylistTrack>, Error> {
+        if let Some(tracks) = self.cache.playlist_tracks(playlist) {
+            return Ok(tracks);
+        }
+        let tracks = self.fetch_playlist_tracks(playlist)?;
+        self.cache.set_playlist_tracks(playlist, &tracks);
+        Ok(tracks)
+    }
+
+    fn fetch_playlist_tracks(
+        &self,
+        playlist: &SimplifiedPlaylist,
+    ) -> Result<Vec<PlaylistTrack>, Error> {
+        let mut tracks = Vec::new();
+        loop {
+            let page = self.api.user_playlist_tracks(
+                &playlist.owner.id,
+                &playlist.id,
+                None,
+                100,
+                tracks.len() as u32,
+                None,
+            )?;
+            let done = page.next.is_none();
+            tracks.extend(page.items);
+            if done {
+                break;
+            }
+        }
+        Ok(tracks)
+    }
+
+    // the playlist modifications return the new snapshot id of the playlist
+
+    pub fn playlist_add_tracks(
+        &self,
+        playlist: &SimplifiedPlaylist,
+        track_ids: &[String],
+    ) -> Result<String, Error> {
+        self.add_tracks(
+            &playlist.owner.id,
+            &playlist.id,
+            track_ids,
+            playlist.snapshot_id.clone(),
+        )
+    }
+
+    // the API accepts at most 100 tracks per request
+    fn add_tracks(
+        &self,
+        owner_id: &str,
+        playlist_id: &str,
+        track_ids: &[String],
+        snapshot_id: String,
+    ) -> Result<String, Error> {
+        let mut snapshot_id = snapshot_id;
+        for chunk in track_ids.chunks(100) {
+            snapshot_id = self
+                .api
+                .user_playlist_add_tracks(owner_id, playlist_id, chunk, None)?
+                .snapshot_id;
+        }
+        Ok(snapshot_id)
+    }
+
+    pub fn playlist_create(&self, name: &str, track_ids: &[String]) -> Result<(), Error> {
+        let playlist = self.api.user_playlist_create(&self.user, name, None, None)?;
+        self.add_tracks(&self.user, &playlist.id, track_ids, playlist.snapshot_id)?;
+        Ok(())
+    }
+
+    pub fn playlist_rename(&self, playlist: &SimplifiedPlaylist, name: &str) -> Result<(), Error> {
+        self.api.user_playlist_change_detail(
+            &playlist.owner.id,
+            &playlist.id,
+            Some(name),
+            None,
+            None,
+            None,
+        )?;
+        Ok(())
+    }
+
+    // playlists can't be deleted, they are only unfollowed by their owner
+    pub fn playlist_delete(&self, playlist: &SimplifiedPlaylist) -> Result<(), Error> {
+        self.api
+            .user_playlist_unfollow(&playlist.owner.id, &playlist.id)?;
+        Ok(())
+    }
+
+    /// Removes the given occurrences of tracks, each identified by the track
+    /// and its position in the playlist.
+    pub fn playlist_remove_tracks(
+        &self,
+        playlist: &SimplifiedPlaylist,
+        tracks: &[(&FullTrack, usize)],
+    ) -> Result<String, Error> {
+        // every track is listed once, along with all positions it is removed
+        // from
+        let mut occurrences: Vec<Map<String, Value>> = Vec::new();
+        for &(track, position) in tracks {
+            let existing = occurrences
+                .iter_mut()
+                .find(|occurrence| occurrence["uri"] == json!(track.uri));
+            match existing {
+                Some(occurrence) => {
+                    if let Some(Value::Array(positions)) = occurrence.get_mut("positions") {
+                        positions.push(json!(position));
+                    }
+                }
+                None => {
+                    let mut occurrence = Map::new();
+                    occurrence.insert("uri".to_string(), json!(track.uri));
+                    occurrence.insert("positions".to_string(), json!([position]));
+                    occurrences.push(occurrence);
+                }
+            }
+        }
+
+        self.api
+            .user_playlist_remove_specific_occurrenes_of_tracks(
+                &playlist.owner.id,
+                &playlist.id,
+                occurrences,
+                Some(playlist.snapshot_id.clone()),
+            )
+            .map(|result| result.snapshot_id)
+    }
+
+    pub fn playlist_move_track(
+        &self,
+        playlist: &SimplifiedPlaylist,
+        position: usize,
+        insert_before: usize,
+    ) -> Result<String, Error> {
+        self.api
+            .user_playlist_recorder_tracks(
+                &playlist.owner.id,
+                &playlist.id,
+                position as i32,
+                Some(1),
+                insert_before as i32,
+                Some(playlist.snapshot_id.clone()),
+            )
+            .map(|result| result.snapshot_id)
+    }
+
+    pub fn current_user_saved_albums(&self) -> Result<Vec<FullAlbum>, Error> {
+        let mut albums = Vec::new();
+        loop {
+            let page = self
+                .api
+                .current_user_saved_albums(50, albums.len() as u32)?;
+            let done = page.next.is_none();
+            albums.extend(page.items.into_iter().map(|saved| saved.album));
+            if done {
+                break;
+            }
+        }
+        Ok(albums)
+    }
+
+    pub fn current_user_followed_artists(&self) -> Result<Vec<FullArtist>, Error> {
+        let mut artists = Vec::new();
+        let mut after = None;
+        loop {
+            let page = self.api.current_user_followed_artists(50, after)?.artists;
+            artists.extend(page.items);
+            after = page.cursors.after;
+            if page.next.is_none() || after.is_none() {
+                break;
+            }
+        }
+        Ok(artists)
+    }
+
+    pub fn album(&self, album_id: &str) -> Result<FullAlbum, Error> {
+        self.api.album(album_id)
+    }
+
+    pub fn artist(&self, artist_id: &str) -> Result<FullArtist, Error> {
+        self.api.artist(artist_id)
+    }
+
+    pub fn artist_related_artists(&self, artist_id: &str) -> Result<Vec<FullArtist>, Error> {
+        self.api
+            .artist_related_artists(artist_id)
+            .map(|related| related.artists)
+    }
+
+    pub fn artist_top_tracks(&self, artist_id: &str) -> Result<Vec<FullTrack>, Error> {
+        self.api
+            .artist_top_tracks(artist_id, None)
+            .map(|toptracks| toptracks.tracks)
+    }
+
+    pub fn artist_albums(
+        &self,
+        artist_id: &str,
+        album_type: Option<AlbumType>,
+    ) -> Result<Vec<SimplifiedAlbum>, Error> {
+        let mut albums = Vec::new();
+        loop {
+            let page = self.api.artist_albums(
+                artist_id,
+                album_type.clone(),
+                None,
+                Some(50),
+                Some(albums.len() as u32),
+            )?;
+            let done = page.next.is_none();
+            albums.extend(page.items);
+            if done {
+                break;
+            }
+        }
+        Ok(albums)
+    }
+
+    // the album endpoints only return simplified tracks, but the queue and the
+    // player work on full tracks, so these are looked up in a second step
+    pub fn album_tracks(&self, album_id: &str) -> Result<Vec<FullTrack>, Error> {
+        let mut ids = Vec::new();
+        loop {
+            let page = self.api.album_track(album_id, 50, ids.len() as u32)?;
+            let done = page.next.is_none();
+            ids.extend(page.items.into_iter().map(|track| track.id));
+            if done {
+                break;
+            }
+        }
+        self.tracks(&ids)
+    }
+
+    pub fn tracks(&self, ids: &[String]) -> Result<Vec<FullTrack>, Error> {
+        let mut tracks = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(50) {
+            let chunk = chunk.iter().map(|id| id.as_str()).collect();
+            tracks.extend(self.api.tracks(chunk, None)?.tracks);
+        }
+        Ok(tracks)
+    }
+
     pub fn load(&self, track: SpotifyId) {
         info!("loading track: {:?}", track);
         self.channel
@@ -259,6 +680,49 @@ impl Spotify {
         *state = newstate;
     }
 
+    pub fn set_current_track(&self, track: Option<&FullTrack>) {
+        let mut current_track = self
+            .current_track
+            .write()
+            .expect("could not acquire write lock on current track");
+        *current_track = track.map(|track| track.id.clone());
+    }
+
+    /// Returns the id of the track that is playing or paused.
+    pub fn current_track_id(&self) -> Option<String> {
+        self.current_track
+            .read()
+            .expect("could not acquire read lock on current track")
+            .clone()
+    }
+
+    /// Loads the track that is played next ahead of time, so it starts
+    /// without a gap once the current one ends.
+    pub fn preload(&self, track: &FullTrack) {
+        if let Ok(id) = SpotifyId::from_base62(&track.id) {
+            self.channel
+                .unbounded_send(WorkerCommand::Preload(id))
+                .unwrap();
+        }
+    }
+
+    /// Drops the preloaded track, e.g. because the queue changed.
+    pub fn cancel_preload(&self) {
+        info!("cancel_preload()");
+        self.channel
+            .unbounded_send(WorkerCommand::CancelPreload)
+            .unwrap();
+    }
+
+    /// Starts the next track of the queue while the current one fades out.
+    /// This only has an effect if crossfading is enabled.
+    pub fn crossfade(&self) {
+        info!("crossfade()");
+        self.channel
+            .unbounded_send(WorkerCommand::Crossfade)
+            .unwrap();
+    }
+
     pub fn play(&self) {
         info!("play()");
         self.channel.unbounded_send(WorkerCommand::Play).unwrap();
@@ -1,18 +1,205 @@
+use cursive::theme::BaseColor::*;
 use cursive::theme::Color::*;
 use cursive::theme::PaletteColor::*;
 use cursive::theme::*;
 
+use log::error;
+
+use crate::config::ThemeConfig;
+
+// names of the colours that aren't part of the cursive palette
+const STATUSBAR: &str = "statusbar";
+const STATUSBAR_BG: &str = "statusbar_bg";
+const PLAYING: &str = "playing";
+
 pub fn default() -> Theme {
-    let mut palette = Palette::default();
-    let borders = BorderStyle::None;
+    theme(builtin("terminal").expect("the default theme is missing"))
+}
+
+/// Builds the theme described by the `[theme]` section of the configuration.
+pub fn load(cfg: &Option<ThemeConfig>) -> Theme {
+    let cfg = match cfg {
+        Some(cfg) => cfg,
+        None => return default(),
+    };
+
+    let mut palette = cfg
+        .name
+        .as_ref()
+        .and_then(|name| {
+            let palette = builtin(name);
+            if palette.is_none() {
+                error!("unknown theme {}", name);
+            }
+            palette
+        })
+        .unwrap_or_else(|| builtin("terminal").unwrap());
+
+    let colors = [
+        (&cfg.primary, Some(Primary), None),
+        (&cfg.secondary, Some(Secondary), None),
+        (&cfg.title, Some(TitlePrimary), None),
+        (&cfg.highlight, Some(Highlight), None),
+        (&cfg.highlight_inactive, Some(HighlightInactive), None),
+        (&cfg.statusbar, None, Some(STATUSBAR)),
+        (&cfg.statusbar_bg, None, Some(STATUSBAR_BG)),
+        (&cfg.playing, None, Some(PLAYING)),
+    ];
+    for (value, role, name) in colors.iter() {
+        let color = match value.as_ref().and_then(|value| parse_color(value)) {
+            Some(color) => color,
+            None => continue,
+        };
+        if let Some(role) = role {
+            palette[*role] = color;
+        }
+        if let Some(name) = name {
+            palette.set_color(name, color);
+        }
+    }
+    if let Some(color) = cfg.background.as_ref().and_then(|value| parse_color(value)) {
+        palette[Background] = color;
+        palette[View] = color;
+    }
+
+    theme(palette)
+}
+
+/// Style of the statusbar.
+pub fn statusbar(theme: &Theme) -> ColorStyle {
+    ColorStyle::new(
+        custom_color(theme, STATUSBAR, Primary),
+        custom_color(theme, STATUSBAR_BG, View),
+    )
+}
 
-    palette[Background] = TerminalDefault;
-    palette[View] = TerminalDefault;
-    palette[Primary] = TerminalDefault;
+/// Style of the track that is currently playing.
+pub fn playing(theme: &Theme) -> ColorStyle {
+    ColorStyle::new(custom_color(theme, PLAYING, Secondary), View)
+}
+
+fn custom_color(theme: &Theme, name: &str, fallback: PaletteColor) -> ColorType {
+    match theme.palette.custom(name) {
+        Some(color) => ColorType::Color(*color),
+        None => ColorType::Palette(fallback),
+    }
+}
 
+fn theme(palette: Palette) -> Theme {
     Theme {
         shadow: false,
-        palette: palette,
-        borders: borders,
+        palette,
+        borders: BorderStyle::None,
+    }
+}
+
+// returns one of the built-in themes
+fn builtin(name: &str) -> Option<Palette> {
+    let mut palette = Palette::default();
+    match name {
+        // the colours of the terminal
+        "terminal" => {
+            palette[Background] = TerminalDefault;
+            palette[View] = TerminalDefault;
+            palette[Primary] = TerminalDefault;
+            palette.set_color(STATUSBAR, TerminalDefault);
+            palette.set_color(STATUSBAR_BG, TerminalDefault);
+            palette.set_color(PLAYING, Light(Green));
+        }
+        "dark" => {
+            palette[Background] = Dark(Black);
+            palette[View] = Dark(Black);
+            palette[Primary] = Light(White);
+            palette[Secondary] = Light(Blue);
+            palette[TitlePrimary] = Light(Cyan);
+            palette[Highlight] = Dark(Blue);
+            palette[HighlightInactive] = Light(Black);
+            palette.set_color(STATUSBAR, Light(White));
+            palette.set_color(STATUSBAR_BG, Light(Black));
+            palette.set_color(PLAYING, Light(Green));
+        }
+        "light" => {
+            palette[Background] = Light(White);
+            palette[View] = Light(White);
+            palette[Primary] = Dark(Black);
+            palette[Secondary] = Dark(Blue);
+            palette[TitlePrimary] = Dark(Magenta);
+            palette[Highlight] = Light(Blue);
+            palette[HighlightInactive] = Dark(White);
+            palette.set_color(STATUSBAR, Dark(Black));
+            palette.set_color(STATUSBAR_BG, Dark(White));
+            palette.set_color(PLAYING, Dark(Green));
+        }
+        _ => return None,
+    }
+    Some(palette)
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    let color = match value {
+        "default" => Some(TerminalDefault),
+        _ if value.starts_with('#') => parse_hex(&value[1..]),
+        _ => match value.parse::<u8>() {
+            Ok(index) => Some(Color::from_256colors(index)),
+            Err(_) if value.starts_with("light ") => parse_base(&value[6..]).map(Light),
+            Err(_) => parse_base(value).map(Dark),
+        },
+    };
+    if color.is_none() {
+        error!("invalid colour {}", value);
+    }
+    color
+}
+
+fn parse_base(name: &str) -> Option<BaseColor> {
+    match name {
+        "black" => Some(Black),
+        "red" => Some(Red),
+        "green" => Some(Green),
+        "yellow" => Some(Yellow),
+        "blue" => Some(Blue),
+        "magenta" => Some(Magenta),
+        "cyan" => Some(Cyan),
+        "white" => Some(White),
+        _ => None,
+    }
+}
+
+// parses `rrggbb` or the short form `rgb`
+fn parse_hex(hex: &str) -> Option<Color> {
+    let (digits, scale) = match hex.len() {
+        6 => (2, 1),
+        3 => (1, 17),
+        _ => return None,
+    };
+    let component = |i: usize| {
+        hex.get(i * digits..(i + 1) * digits)
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            .map(|value| value * scale)
+    };
+    Some(Rgb(component(0)?, component(1)?, component(2)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colours_are_parsed_in_all_forms() {
+        assert_eq!(parse_color("default"), Some(TerminalDefault));
+        assert_eq!(parse_color("cyan"), Some(Dark(Cyan)));
+        assert_eq!(parse_color("light white"), Some(Light(White)));
+        assert_eq!(parse_color("#8080ff"), Some(Rgb(0x80, 0x80, 0xff)));
+        assert_eq!(parse_color("#f08"), Some(Rgb(0xff, 0x00, 0x88)));
+        assert_eq!(parse_color("240"), Some(Color::from_256colors(240)));
+    }
+
+    #[test]
+    fn invalid_colours_are_rejected() {
+        assert_eq!(parse_color("purple"), None);
+        assert_eq!(parse_color("light"), None);
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("#gg0000"), None);
+        assert_eq!(parse_color("256"), None);
     }
 }
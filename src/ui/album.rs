@@ -0,0 +1,160 @@
+use cursive::direction::Orientation;
+use cursive::event::Key;
+use cursive::traits::Boxable;
+use cursive::traits::Identifiable;
+use cursive::views::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use log::error;
+
+use rspotify::spotify::model::album::FullAlbum;
+use rspotify::spotify::model::track::FullTrack;
+
+use crate::events::{Event, EventManager};
+use crate::queue::Queue;
+use crate::spotify::Spotify;
+use crate::ui::artist;
+use crate::ui::tracklist::{format_duration, join_names, Column, TrackList};
+
+pub struct AlbumView {
+    pub view: OnEventView<Panel<LinearLayout>>,
+}
+
+// album views can be stacked on top of each other, so every instance needs
+// its own id for the callbacks to find the right list
+static VIEW_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+impl AlbumView {
+    pub fn open(
+        spotify: Arc<Spotify>,
+        queue: Arc<Mutex<Queue>>,
+        event_manager: EventManager,
+        album_id: &str,
+    ) -> Option<AlbumView> {
+        let album = match spotify.album(album_id) {
+            Ok(album) => album,
+            Err(e) => {
+                error!("could not load album {}: {}", album_id, e);
+                return None;
+            }
+        };
+        let mut tracks = match spotify.album_tracks(album_id) {
+            Ok(tracks) => tracks,
+            Err(e) => {
+                error!("could not load tracks of album {}: {}", album.name, e);
+                return None;
+            }
+        };
+        tracks.sort_by_key(|track| (track.disc_number, track.track_number));
+
+        Some(AlbumView::new(spotify, queue, event_manager, album, tracks))
+    }
+
+    pub fn new(
+        spotify: Arc<Spotify>,
+        queue: Arc<Mutex<Queue>>,
+        event_manager: EventManager,
+        album: FullAlbum,
+        tracks: Vec<FullTrack>,
+    ) -> AlbumView {
+        let list_id = format!("album_tracks_{}", VIEW_COUNT.fetch_add(1, Ordering::Relaxed));
+        let tracks = Arc::new(tracks);
+
+        let mut tracklist = TrackList::new(&[
+            Column::Number,
+            Column::Title,
+            Column::Artist,
+            Column::Duration,
+        ]);
+        for (i, track) in tracks.iter().enumerate() {
+            tracklist.add_row(i, track.clone(), None);
+        }
+        tracklist.show_playing(spotify);
+
+        // <enter> plays the album starting at the selected track
+        {
+            let queue = queue.clone();
+            let tracks = tracks.clone();
+            let event_manager = event_manager.clone();
+            tracklist.set_on_submit(move |_siv, i| {
+                event_manager.send(Event::Play(tracks[i].clone()));
+                queue.lock().unwrap().replace(tracks[i + 1..].to_vec());
+            });
+        }
+
+        let total_ms: u32 = tracks.iter().map(|track| track.duration_ms).sum();
+        let header = TextView::new(format!(
+            "{}\nReleased {}, {} tracks, {}",
+            join_names(&album.artists),
+            album.release_date,
+            tracks.len(),
+            format_duration(total_ms),
+        ));
+
+        let layout = LinearLayout::new(Orientation::Vertical)
+            .child(header)
+            .child(DummyView)
+            .child(
+                tracklist
+                    .with_id(list_id.as_str())
+                    .full_width()
+                    .full_height(),
+            );
+
+        let rootpanel = Panel::new(layout).title(format!("Album: {}", album.name));
+        let mut view = OnEventView::new(rootpanel);
+
+        // <a> queues the whole album
+        {
+            let tracks = tracks.clone();
+            view.set_on_event('a', move |_siv| {
+                let mut queue = queue.lock().unwrap();
+                for track in tracks.iter() {
+                    queue.enqueue(track.clone());
+                }
+            });
+        }
+
+        // <A> shows the artist of the selected track
+        {
+            let list_id = list_id.clone();
+            let event_manager = event_manager.clone();
+            view.set_on_event('A', move |siv| {
+                let selected = siv
+                    .call_on_id(&list_id, |tracklist: &mut TrackList| tracklist.selected_id())
+                    .and_then(|selected| selected);
+                if let Some(i) = selected {
+                    artist::select_artist(siv, &event_manager, &tracks[i].artists);
+                }
+            });
+        }
+
+        // <o> jumps to the track that is playing
+        {
+            let list_id = list_id.clone();
+            view.set_on_event('o', move |siv| {
+                siv.call_on_id(&list_id, |tracklist: &mut TrackList| tracklist.select_playing());
+            });
+        }
+
+        // <esc> closes the page
+        view.set_on_event(Key::Esc, move |_siv| {
+            event_manager.send(Event::NavigateBack);
+        });
+
+        AlbumView { view }
+    }
+}
+
+/// Fetches the tracks of an album in the order they are on it, for the views
+/// that list albums. Errors are logged and leave the album empty.
+pub fn load_album_tracks(spotify: &Spotify, album_id: &str, album_name: &str) -> Vec<FullTrack> {
+    let mut tracks = spotify.album_tracks(album_id).unwrap_or_else(|e| {
+        error!("could not load tracks of album {}: {}", album_name, e);
+        Vec::new()
+    });
+    tracks.sort_by_key(|track| (track.disc_number, track.track_number));
+    tracks
+}
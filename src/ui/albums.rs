@@ -0,0 +1,145 @@
+use cursive::direction::Orientation;
+use cursive::traits::Boxable;
+use cursive::traits::Identifiable;
+use cursive::views::*;
+use cursive::Cursive;
+use cursive_tree_view::{Placement, TreeView};
+use derive_more::Display;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use log::error;
+
+use rspotify::spotify::model::album::FullAlbum;
+use rspotify::spotify::model::artist::SimplifiedArtist;
+use rspotify::spotify::model::track::FullTrack;
+
+use crate::events::{Event, EventManager};
+use crate::queue::Queue;
+use crate::spotify::Spotify;
+use crate::ui::album::load_album_tracks;
+use crate::ui::tracklist::{self, Column, TrackEntry, TreeLayout, TreeRow, TreeTrack};
+
+pub struct AlbumsView {
+    pub view: Panel<LinearLayout>,
+}
+
+#[derive(Debug, Display, Clone)]
+pub enum AlbumEntry {
+    #[display(fmt = "{} - {}", "tracklist::join_names(&_0.artists)", "_0.name")]
+    Album(FullAlbum),
+    #[display(fmt = "{}", "_0")]
+    Track(TreeTrack),
+}
+
+// columns of the track rows
+const ALBUM_COLUMNS: [Column; 4] = [
+    Column::Number,
+    Column::Title,
+    Column::Artist,
+    Column::Duration,
+];
+
+const TREE_ID: &str = "albums_tree";
+
+impl AlbumsView {
+    pub fn new(
+        spotify: Arc<Spotify>,
+        queue: Arc<Mutex<Queue>>,
+        event_manager: EventManager,
+    ) -> AlbumsView {
+        let mut tree_view = TreeView::new();
+
+        match spotify.current_user_saved_albums() {
+            Ok(albums) => {
+                for (i, album) in albums.into_iter().enumerate() {
+                    tree_view.insert_container_item(AlbumEntry::Album(album), Placement::After, i);
+                }
+            }
+            Err(e) => error!("could not load saved albums: {}", e),
+        }
+
+        {
+            let event_manager = event_manager.clone();
+            tree_view.set_on_submit(move |siv: &mut Cursive, row: usize| {
+                siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<AlbumEntry>| {
+                    if let Some(&AlbumEntry::Track(ref track)) = tree_view.borrow_item(row) {
+                        event_manager.send(Event::Play(track.track.clone()));
+                    }
+                });
+            });
+        }
+
+        {
+            let spotify = spotify.clone();
+            tree_view.set_on_collapse(move |siv: &mut Cursive, row, is_collapsed, children| {
+                if !is_collapsed && children == 0 {
+                    siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<AlbumEntry>| {
+                        let tracks = match tree_view.borrow_item(row) {
+                            Some(&AlbumEntry::Album(ref album)) => {
+                                load_album_tracks(&spotify, &album.id, &album.name)
+                            }
+                            _ => Vec::new(),
+                        };
+                        for track in tracks {
+                            tree_view.insert_item(
+                                AlbumEntry::Track(TreeTrack::new(track, &ALBUM_COLUMNS, 1)),
+                                Placement::LastChild,
+                                row,
+                            );
+                        }
+                    });
+                }
+            });
+        }
+
+        let tree = TreeLayout::new(tree_view.with_id(TREE_ID)).show_playing(spotify.clone());
+        let view =
+            tracklist::tree_keys::<AlbumEntry, _>(tree, TREE_ID, spotify, queue, event_manager);
+
+        let layout = LinearLayout::new(Orientation::Vertical).child(view.full_width());
+
+        let rootpanel = Panel::new(layout).title("Albums");
+        AlbumsView { view: rootpanel }
+    }
+}
+
+impl TrackEntry for AlbumEntry {
+    fn track(&self) -> Option<&FullTrack> {
+        match *self {
+            AlbumEntry::Track(ref track) => Some(&track.track),
+            _ => None,
+        }
+    }
+
+    // tracks are fetched here rather than taken from the tree, so collapsed
+    // albums are queued as well
+    fn tracks(&self, spotify: &Spotify) -> Vec<FullTrack> {
+        match *self {
+            AlbumEntry::Album(ref album) => load_album_tracks(spotify, &album.id, &album.name),
+            AlbumEntry::Track(ref track) => vec![track.track.clone()],
+        }
+    }
+
+    fn album_id(&self) -> Option<String> {
+        match *self {
+            AlbumEntry::Album(ref album) => Some(album.id.clone()),
+            AlbumEntry::Track(ref track) => Some(track.track.album.id.clone()),
+        }
+    }
+
+    fn artists(&self) -> Vec<SimplifiedArtist> {
+        match *self {
+            AlbumEntry::Album(ref album) => album.artists.clone(),
+            AlbumEntry::Track(ref track) => track.track.artists.clone(),
+        }
+    }
+}
+
+impl TreeRow for AlbumEntry {
+    fn set_layout(&mut self, width: usize, playing: Option<&str>) {
+        if let AlbumEntry::Track(ref mut track) = *self {
+            track.set_layout(width, playing);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use cursive::direction::Orientation;
+use cursive::event::Key;
+use cursive::traits::Boxable;
+use cursive::traits::Identifiable;
+use cursive::views::*;
+use cursive::Cursive;
+use cursive_tree_view::{Placement, TreeView};
+use derive_more::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use log::error;
+
+use rspotify::spotify::model::album::SimplifiedAlbum;
+use rspotify::spotify::model::artist::{FullArtist, SimplifiedArtist};
+use rspotify::spotify::model::track::FullTrack;
+use rspotify::spotify::senum::AlbumType;
+
+use crate::events::{Event, EventManager};
+use crate::queue::Queue;
+use crate::spotify::Spotify;
+use crate::ui::album::load_album_tracks;
+use crate::ui::tracklist::{self, Column, TrackEntry, TreeLayout, TreeRow, TreeTrack};
+
+pub struct ArtistView {
+    pub view: OnEventView<Panel<LinearLayout>>,
+}
+
+#[derive(Debug, Display, Clone)]
+pub enum ArtistViewEntry {
+    #[display(fmt = "{}", "_0")]
+    Group(String),
+    #[display(fmt = "{}", "_0.name")]
+    Album(SimplifiedAlbum),
+    #[display(fmt = "{}", "_0.name")]
+    Artist(FullArtist),
+    #[display(fmt = "{}", "_0")]
+    Track(TreeTrack),
+}
+
+// columns of the track rows
+const TRACK_COLUMNS: [Column; 4] = [
+    Column::Title,
+    Column::Artist,
+    Column::Album,
+    Column::Duration,
+];
+// tracks are laid out for the deepest level they appear at, below the albums,
+// so the top tracks line up with them
+const TRACK_DEPTH: usize = 2;
+
+// artist views can be stacked on top of each other, so every instance needs
+// its own id for the callbacks to find the right tree
+static VIEW_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+impl ArtistView {
+    pub fn open(
+        spotify: Arc<Spotify>,
+        queue: Arc<Mutex<Queue>>,
+        event_manager: EventManager,
+        artist_id: &str,
+    ) -> Option<ArtistView> {
+        match spotify.artist(artist_id) {
+            Ok(artist) => Some(ArtistView::new(spotify, queue, event_manager, artist)),
+            Err(e) => {
+                error!("could not load artist {}: {}", artist_id, e);
+                None
+            }
+        }
+    }
+
+    pub fn new(
+        spotify: Arc<Spotify>,
+        queue: Arc<Mutex<Queue>>,
+        event_manager: EventManager,
+        artist: FullArtist,
+    ) -> ArtistView {
+        let tree_id = format!("artist_tree_{}", VIEW_COUNT.fetch_add(1, Ordering::Relaxed));
+
+        let top_tracks = spotify.artist_top_tracks(&artist.id).unwrap_or_else(|e| {
+            error!("could not load top tracks of artist {}: {}", artist.name, e);
+            Vec::new()
+        });
+        let related = spotify
+            .artist_related_artists(&artist.id)
+            .unwrap_or_else(|e| {
+                error!("could not load artists related to {}: {}", artist.name, e);
+                Vec::new()
+            });
+
+        let mut groups = vec![(
+            "Top tracks",
+            top_tracks
+                .into_iter()
+                .map(|track| {
+                    ArtistViewEntry::Track(TreeTrack::new(track, &TRACK_COLUMNS, TRACK_DEPTH))
+                })
+                .collect::<Vec<ArtistViewEntry>>(),
+        )];
+        for &(title, ref album_type) in &[
+            ("Albums", AlbumType::Album),
+            ("Singles", AlbumType::Single),
+            ("Compilations", AlbumType::Compilation),
+        ] {
+            let albums = spotify
+                .artist_albums(&artist.id, Some(album_type.clone()))
+                .unwrap_or_else(|e| {
+                    error!("could not load {} of {}: {}", title, artist.name, e);
+                    Vec::new()
+                });
+            groups.push((
+                title,
+                albums.into_iter().map(ArtistViewEntry::Album).collect(),
+            ));
+        }
+        groups.push((
+            "Related artists",
+            related.into_iter().map(ArtistViewEntry::Artist).collect(),
+        ));
+        groups.retain(|&(_, ref entries)| !entries.is_empty());
+
+        let mut tree_view = TreeView::new();
+
+        // insert all groups first and fill them from the bottom up, so the
+        // rows of the groups don't shift while their children are added
+        for (i, &(title, _)) in groups.iter().enumerate() {
+            tree_view.insert_container_item(
+                ArtistViewEntry::Group(title.to_string()),
+                Placement::After,
+                i,
+            );
+        }
+        for (row, (_, entries)) in groups.into_iter().enumerate().rev() {
+            for entry in entries {
+                match entry {
+                    ArtistViewEntry::Album(_) => {
+                        tree_view.insert_container_item(entry, Placement::LastChild, row)
+                    }
+                    _ => tree_view.insert_item(entry, Placement::LastChild, row),
+                };
+            }
+        }
+
+        {
+            let tree_id = tree_id.clone();
+            let event_manager = event_manager.clone();
+            tree_view.set_on_submit(move |siv: &mut Cursive, row: usize| {
+                siv.call_on_id(
+                    &tree_id,
+                    |tree_view: &mut TreeView<ArtistViewEntry>| match tree_view.borrow_item(row) {
+                        Some(&ArtistViewEntry::Track(ref track)) => {
+                            event_manager.send(Event::Play(track.track.clone()));
+                        }
+                        Some(&ArtistViewEntry::Artist(ref artist)) => {
+                            event_manager.send(Event::ShowArtist(artist.id.clone()));
+                        }
+                        _ => (),
+                    },
+                );
+            });
+        }
+
+        {
+            let tree_id = tree_id.clone();
+            let spotify = spotify.clone();
+            tree_view.set_on_collapse(move |siv: &mut Cursive, row, is_collapsed, children| {
+                if !is_collapsed && children == 0 {
+                    siv.call_on_id(&tree_id, |tree_view: &mut TreeView<ArtistViewEntry>| {
+                        let tracks = match tree_view.borrow_item(row) {
+                            Some(&ArtistViewEntry::Album(ref album)) => {
+                                load_album_tracks(&spotify, &album.id, &album.name)
+                            }
+                            _ => Vec::new(),
+                        };
+                        for track in tracks {
+                            tree_view.insert_item(
+                                ArtistViewEntry::Track(TreeTrack::new(
+                                    track,
+                                    &TRACK_COLUMNS,
+                                    TRACK_DEPTH,
+                                )),
+                                Placement::LastChild,
+                                row,
+                            );
+                        }
+                    });
+                }
+            });
+        }
+
+        let tree =
+            TreeLayout::new(tree_view.with_id(tree_id.as_str())).show_playing(spotify.clone());
+        let tree = tracklist::tree_keys::<ArtistViewEntry, _>(
+            tree,
+            &tree_id,
+            spotify,
+            queue,
+            event_manager.clone(),
+        );
+
+        let layout = LinearLayout::new(Orientation::Vertical).child(tree.full_width());
+
+        let rootpanel = Panel::new(layout).title(format!("Artist: {}", artist.name));
+
+        // <esc> closes the page
+        let view = OnEventView::new(rootpanel).on_event(Key::Esc, move |_siv| {
+            event_manager.send(Event::NavigateBack);
+        });
+
+        ArtistView { view }
+    }
+}
+
+impl TrackEntry for ArtistViewEntry {
+    fn track(&self) -> Option<&FullTrack> {
+        match *self {
+            ArtistViewEntry::Track(ref track) => Some(&track.track),
+            _ => None,
+        }
+    }
+
+    fn tracks(&self, spotify: &Spotify) -> Vec<FullTrack> {
+        match *self {
+            ArtistViewEntry::Album(ref album) => load_album_tracks(spotify, &album.id, &album.name),
+            ArtistViewEntry::Track(ref track) => vec![track.track.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    fn album_id(&self) -> Option<String> {
+        match *self {
+            ArtistViewEntry::Album(ref album) => Some(album.id.clone()),
+            ArtistViewEntry::Track(ref track) => Some(track.track.album.id.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl TreeRow for ArtistViewEntry {
+    fn set_layout(&mut self, width: usize, playing: Option<&str>) {
+        if let ArtistViewEntry::Track(ref mut track) = *self {
+            track.set_layout(width, playing);
+        }
+    }
+}
+
+/// Opens the artist page of a track or album. If there are several artists, a
+/// dialog to pick one of them is shown first.
+pub fn select_artist(
+    siv: &mut Cursive,
+    event_manager: &EventManager,
+    artists: &[SimplifiedArtist],
+) {
+    match artists.len() {
+        0 => (),
+        1 => event_manager.send(Event::ShowArtist(artists[0].id.clone())),
+        _ => {
+            let mut select = SelectView::<String>::new();
+            for artist in artists {
+                select.add_item(artist.name.clone(), artist.id.clone());
+            }
+
+            let event_manager = event_manager.clone();
+            select.set_on_submit(move |siv, artist_id: &String| {
+                siv.pop_layer();
+                event_manager.send(Event::ShowArtist(artist_id.clone()));
+            });
+
+            siv.add_layer(
+                Dialog::around(select)
+                    .title("Show artist")
+                    .dismiss_button("Cancel"),
+            );
+        }
+    }
+}
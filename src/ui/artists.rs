@@ -0,0 +1,206 @@
+use cursive::direction::Orientation;
+use cursive::traits::Boxable;
+use cursive::traits::Identifiable;
+use cursive::views::*;
+use cursive::Cursive;
+use cursive_tree_view::{Placement, TreeView};
+use derive_more::Display;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use log::error;
+
+use rspotify::spotify::model::album::SimplifiedAlbum;
+use rspotify::spotify::model::artist::{FullArtist, SimplifiedArtist};
+use rspotify::spotify::model::track::FullTrack;
+
+use crate::events::{Event, EventManager};
+use crate::queue::Queue;
+use crate::spotify::Spotify;
+use crate::ui::album::load_album_tracks;
+use crate::ui::tracklist::{self, Column, TrackEntry, TreeLayout, TreeRow, TreeTrack};
+
+pub struct ArtistsView {
+    pub view: Panel<LinearLayout>,
+}
+
+#[derive(Debug, Display, Clone)]
+pub enum ArtistEntry {
+    #[display(fmt = "{}", "_0.name")]
+    Artist(FullArtist),
+    // the artist id is kept so the contents can be fetched on expansion
+    #[display(fmt = "Top tracks")]
+    TopTracks(String),
+    #[display(fmt = "Discography")]
+    Discography(String),
+    #[display(fmt = "{}", "_0.name")]
+    Album(SimplifiedAlbum),
+    #[display(fmt = "{}", "_0")]
+    Track(TreeTrack),
+}
+
+// columns of the track rows
+const TRACK_COLUMNS: [Column; 4] = [
+    Column::Title,
+    Column::Artist,
+    Column::Album,
+    Column::Duration,
+];
+// tracks are laid out for the deepest level they appear at, below the albums
+// of a discography, so the top tracks line up with them
+const TRACK_DEPTH: usize = 3;
+
+const TREE_ID: &str = "artists_tree";
+
+impl ArtistsView {
+    pub fn new(
+        spotify: Arc<Spotify>,
+        queue: Arc<Mutex<Queue>>,
+        event_manager: EventManager,
+    ) -> ArtistsView {
+        let mut tree_view = TreeView::new();
+
+        match spotify.current_user_followed_artists() {
+            Ok(artists) => {
+                for (i, artist) in artists.into_iter().enumerate() {
+                    tree_view.insert_container_item(
+                        ArtistEntry::Artist(artist),
+                        Placement::After,
+                        i,
+                    );
+                }
+            }
+            Err(e) => error!("could not load followed artists: {}", e),
+        }
+
+        {
+            let event_manager = event_manager.clone();
+            tree_view.set_on_submit(move |siv: &mut Cursive, row: usize| {
+                siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<ArtistEntry>| {
+                    if let Some(&ArtistEntry::Track(ref track)) = tree_view.borrow_item(row) {
+                        event_manager.send(Event::Play(track.track.clone()));
+                    }
+                });
+            });
+        }
+
+        {
+            let spotify = spotify.clone();
+            tree_view.set_on_collapse(move |siv: &mut Cursive, row, is_collapsed, children| {
+                if !is_collapsed && children == 0 {
+                    siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<ArtistEntry>| {
+                        ArtistsView::expand(&spotify, tree_view, row);
+                    });
+                }
+            });
+        }
+
+        let tree = TreeLayout::new(tree_view.with_id(TREE_ID)).show_playing(spotify.clone());
+        let view =
+            tracklist::tree_keys::<ArtistEntry, _>(tree, TREE_ID, spotify, queue, event_manager);
+
+        let layout = LinearLayout::new(Orientation::Vertical).child(view.full_width());
+
+        let rootpanel = Panel::new(layout).title("Artists");
+        ArtistsView { view: rootpanel }
+    }
+
+    fn expand(spotify: &Spotify, tree_view: &mut TreeView<ArtistEntry>, row: usize) {
+        let entry = tree_view.borrow_item(row).cloned();
+        match entry {
+            Some(ArtistEntry::Artist(artist)) => {
+                tree_view.insert_container_item(
+                    ArtistEntry::TopTracks(artist.id.clone()),
+                    Placement::LastChild,
+                    row,
+                );
+                tree_view.insert_container_item(
+                    ArtistEntry::Discography(artist.id),
+                    Placement::LastChild,
+                    row,
+                );
+            }
+            Some(ArtistEntry::TopTracks(id)) => {
+                for track in ArtistsView::load_top_tracks(spotify, &id) {
+                    let track = TreeTrack::new(track, &TRACK_COLUMNS, TRACK_DEPTH);
+                    tree_view.insert_item(ArtistEntry::Track(track), Placement::LastChild, row);
+                }
+            }
+            Some(ArtistEntry::Discography(id)) => match spotify.artist_albums(&id, None) {
+                Ok(albums) => {
+                    for album in albums {
+                        tree_view.insert_container_item(
+                            ArtistEntry::Album(album),
+                            Placement::LastChild,
+                            row,
+                        );
+                    }
+                }
+                Err(e) => error!("could not load albums of artist {}: {}", id, e),
+            },
+            Some(ArtistEntry::Album(album)) => {
+                for track in load_album_tracks(spotify, &album.id, &album.name) {
+                    let track = TreeTrack::new(track, &TRACK_COLUMNS, TRACK_DEPTH);
+                    tree_view.insert_item(ArtistEntry::Track(track), Placement::LastChild, row);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn load_top_tracks(spotify: &Spotify, artist_id: &str) -> Vec<FullTrack> {
+        spotify.artist_top_tracks(artist_id).unwrap_or_else(|e| {
+            error!("could not load top tracks of artist {}: {}", artist_id, e);
+            Vec::new()
+        })
+    }
+}
+
+impl TrackEntry for ArtistEntry {
+    fn track(&self) -> Option<&FullTrack> {
+        match *self {
+            ArtistEntry::Track(ref track) => Some(&track.track),
+            _ => None,
+        }
+    }
+
+    fn tracks(&self, spotify: &Spotify) -> Vec<FullTrack> {
+        match *self {
+            ArtistEntry::TopTracks(ref id) => ArtistsView::load_top_tracks(spotify, id),
+            ArtistEntry::Album(ref album) => load_album_tracks(spotify, &album.id, &album.name),
+            ArtistEntry::Track(ref track) => vec![track.track.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    fn album_id(&self) -> Option<String> {
+        match *self {
+            ArtistEntry::Album(ref album) => Some(album.id.clone()),
+            ArtistEntry::Track(ref track) => Some(track.track.album.id.clone()),
+            _ => None,
+        }
+    }
+
+    fn artists(&self) -> Vec<SimplifiedArtist> {
+        match *self {
+            ArtistEntry::Artist(ref artist) => vec![SimplifiedArtist {
+                external_urls: artist.external_urls.clone(),
+                href: artist.href.clone(),
+                id: artist.id.clone(),
+                name: artist.name.clone(),
+                _type: artist._type,
+                uri: artist.uri.clone(),
+            }],
+            ArtistEntry::Track(ref track) => track.track.artists.clone(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl TreeRow for ArtistEntry {
+    fn set_layout(&mut self, width: usize, playing: Option<&str>) {
+        if let ArtistEntry::Track(ref mut track) = *self {
+            track.set_layout(width, playing);
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use cursive::theme::{Color, ColorStyle, ColorType, PaletteColor};
+use cursive::vec::Vec2;
+use cursive::Printer;
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use failure::Error;
+use image::{DynamicImage, FilterType};
+
+use rspotify::spotify::model::image::Image;
+
+use crate::config::{self, CoverMode};
+
+/// The colours a terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Colors {
+    TrueColor,
+    Palette256,
+}
+
+impl Colors {
+    /// Tells the colours of the terminal from `$COLORTERM`.
+    pub fn detect() -> Colors {
+        match env::var("COLORTERM") {
+            Ok(ref value) if value == "truecolor" || value == "24bit" => Colors::TrueColor,
+            _ => Colors::Palette256,
+        }
+    }
+
+    fn color(self, rgb: [u8; 3]) -> Color {
+        match self {
+            Colors::TrueColor => Color::Rgb(rgb[0], rgb[1], rgb[2]),
+            Colors::Palette256 => Color::from_256colors(palette_index(rgb)),
+        }
+    }
+}
+
+// returns the closest colour of the 6x6x6 cube of the 256 colour palette
+fn palette_index(rgb: [u8; 3]) -> u8 {
+    let level = |value: u8| ((u16::from(value) * 5 + 127) / 255) as u8;
+    16 + 36 * level(rgb[0]) + 6 * level(rgb[1]) + level(rgb[2])
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Cell {
+    ch: char,
+    fg: [u8; 3],
+    // cells without a background keep the one of the view
+    bg: Option<[u8; 3]>,
+}
+
+/// An image rendered to characters.
+#[derive(Clone, Debug)]
+pub struct Art {
+    pub size: Vec2,
+    cells: Vec<Cell>,
+}
+
+impl Art {
+    /// Renders an image to fit into `size` characters, keeping its aspect
+    /// ratio with characters being twice as high as they are wide.
+    pub fn render(image: &DynamicImage, size: Vec2, mode: CoverMode) -> Art {
+        match mode {
+            _ if size.x == 0 || size.y == 0 => Art::empty(),
+            CoverMode::HalfBlock => Art::half_blocks(image, size),
+            CoverMode::Braille => Art::braille(image, size),
+            CoverMode::None => Art::empty(),
+        }
+    }
+
+    fn empty() -> Art {
+        Art {
+            size: Vec2::zero(),
+            cells: Vec::new(),
+        }
+    }
+
+    fn half_blocks(image: &DynamicImage, size: Vec2) -> Art {
+        let image = image
+            .resize(size.x as u32, size.y as u32 * 2, FilterType::Triangle)
+            .to_rgb();
+        let (width, height) = image.dimensions();
+        let rows = (height + 1) / 2;
+
+        let mut cells = Vec::with_capacity((width * rows) as usize);
+        for y in 0..rows {
+            for x in 0..width {
+                let top = image.get_pixel(x, y * 2).data;
+                let bottom = if y * 2 + 1 < height {
+                    Some(image.get_pixel(x, y * 2 + 1).data)
+                } else {
+                    None
+                };
+                cells.push(Cell {
+                    ch: '▀',
+                    fg: top,
+                    bg: bottom,
+                });
+            }
+        }
+        Art {
+            size: Vec2::new(width as usize, rows as usize),
+            cells,
+        }
+    }
+
+    fn braille(image: &DynamicImage, size: Vec2) -> Art {
+        let image = image
+            .resize(size.x as u32 * 2, size.y as u32 * 4, FilterType::Triangle)
+            .to_rgb();
+        let (width, height) = image.dimensions();
+        let (columns, rows) = ((width + 1) / 2, (height + 3) / 4);
+
+        let luminance = |rgb: [u8; 3]| {
+            (299 * u32::from(rgb[0]) + 587 * u32::from(rgb[1]) + 114 * u32::from(rgb[2])) / 1000
+        };
+        // dots brighter than the average are set
+        let threshold =
+            image.pixels().map(|p| luminance(p.data)).sum::<u32>() / (width * height).max(1);
+
+        // bits of the dots of a braille pattern, by column and row
+        const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+        let mut cells = Vec::with_capacity((columns * rows) as usize);
+        for row in 0..rows {
+            for column in 0..columns {
+                let mut bits = 0;
+                let mut sum = [0u32; 3];
+                let mut count = 0;
+                for dx in 0..2 {
+                    for dy in 0..4 {
+                        let (x, y) = (column * 2 + dx, row * 4 + dy);
+                        if x >= width || y >= height {
+                            continue;
+                        }
+                        let rgb = image.get_pixel(x, y).data;
+                        if luminance(rgb) > threshold {
+                            bits |= DOTS[dx as usize][dy as usize];
+                            for (total, &value) in sum.iter_mut().zip(rgb.iter()) {
+                                *total += u32::from(value);
+                            }
+                            count += 1;
+                        }
+                    }
+                }
+
+                // the dots take the average colour of the pixels they show
+                let count = count.max(1);
+                cells.push(Cell {
+                    ch: std::char::from_u32(0x2800 + bits).unwrap_or(' '),
+                    fg: [
+                        (sum[0] / count) as u8,
+                        (sum[1] / count) as u8,
+                        (sum[2] / count) as u8,
+                    ],
+                    bg: None,
+                });
+            }
+        }
+        Art {
+            size: Vec2::new(columns as usize, rows as usize),
+            cells,
+        }
+    }
+
+    pub fn draw(&self, printer: &Printer<'_, '_>, colors: Colors) {
+        for (i, cell) in self.cells.iter().enumerate() {
+            let position = (i % self.size.x, i / self.size.x);
+            let back = match cell.bg {
+                Some(bg) => ColorType::Color(colors.color(bg)),
+                None => ColorType::Palette(PaletteColor::View),
+            };
+            let style = ColorStyle::new(ColorType::Color(colors.color(cell.fg)), back);
+            printer.with_color(style, |printer| {
+                printer.print(position, &cell.ch.to_string());
+            });
+        }
+    }
+}
+
+/// Returns the directory covers are cached in.
+pub fn cache_dir() -> Option<PathBuf> {
+    config::cache_dir().map(|dir| dir.join("covers"))
+}
+
+/// Picks the image of an album closest to the size of the rendered cover.
+pub fn pick_image(images: &[Image]) -> Option<&Image> {
+    images
+        .iter()
+        .min_by_key(|image| (i64::from(image.width.unwrap_or(0)) - 300).abs())
+}
+
+/// Loads a cover, downloading it unless it is cached in `dir` already.
+pub fn load(dir: &Path, url: &str) -> Result<DynamicImage, Error> {
+    // the urls end with a unique id of the image
+    let name = url.rsplit('/').next().unwrap_or(url);
+    let path = dir.join(name);
+
+    if !path.exists() {
+        fs::create_dir_all(dir)?;
+        // downloads go to a temporary file first, so an interrupted download
+        // doesn't leave a broken image behind
+        let partial = path.with_extension("part");
+        let mut file = File::create(&partial)?;
+        reqwest::get(url)?.error_for_status()?.copy_to(&mut file)?;
+        fs::rename(&partial, &path)?;
+    }
+
+    // the files have no extension, so the format is told by the contents
+    Ok(image::load_from_memory(&fs::read(&path)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    const RED: [u8; 3] = [255, 0, 0];
+    const WHITE: [u8; 3] = [255, 255, 255];
+    const BLACK: [u8; 3] = [0, 0, 0];
+
+    fn image<F: Fn(u32, u32) -> [u8; 3]>(width: u32, height: u32, pixel: F) -> DynamicImage {
+        DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, height, |x, y| Rgb {
+            data: pixel(x, y),
+        }))
+    }
+
+    #[test]
+    fn nothing_is_rendered_without_space_or_mode() {
+        let image = image(4, 4, |_, _| RED);
+        let art = Art::render(&image, Vec2::new(0, 4), CoverMode::HalfBlock);
+        assert_eq!(art.size, Vec2::zero());
+        let art = Art::render(&image, Vec2::new(4, 4), CoverMode::None);
+        assert_eq!(art.size, Vec2::zero());
+    }
+
+    #[test]
+    fn half_blocks_show_two_pixels_per_character() {
+        // square images are half as high as they are wide in characters
+        let art = Art::render(
+            &image(4, 4, |_, _| RED),
+            Vec2::new(4, 4),
+            CoverMode::HalfBlock,
+        );
+        assert_eq!(art.size, Vec2::new(4, 2));
+        assert_eq!(art.cells.len(), 8);
+        for cell in &art.cells {
+            assert_eq!(cell.ch, '▀');
+            assert_eq!(cell.fg, RED);
+            assert_eq!(cell.bg, Some(RED));
+        }
+    }
+
+    #[test]
+    fn half_blocks_of_odd_height_have_no_background_in_last_row() {
+        let art = Art::render(
+            &image(4, 3, |_, _| RED),
+            Vec2::new(4, 4),
+            CoverMode::HalfBlock,
+        );
+        assert_eq!(art.size, Vec2::new(4, 2));
+        assert!(art.cells[..4].iter().all(|cell| cell.bg == Some(RED)));
+        assert!(art.cells[4..].iter().all(|cell| cell.bg.is_none()));
+    }
+
+    #[test]
+    fn braille_sets_dots_brighter_than_average() {
+        // the left column of the pattern is white, the right one black
+        let image = image(2, 4, |x, _| if x == 0 { WHITE } else { BLACK });
+        let art = Art::render(&image, Vec2::new(1, 1), CoverMode::Braille);
+        assert_eq!(art.size, Vec2::new(1, 1));
+        assert_eq!(art.cells[0].ch, '⡇');
+        assert_eq!(art.cells[0].fg, WHITE);
+        assert_eq!(art.cells[0].bg, None);
+    }
+
+    #[test]
+    fn braille_of_plain_image_is_blank() {
+        let art = Art::render(
+            &image(4, 8, |_, _| RED),
+            Vec2::new(2, 2),
+            CoverMode::Braille,
+        );
+        assert_eq!(art.size, Vec2::new(2, 2));
+        assert!(art.cells.iter().all(|cell| cell.ch == '\u{2800}'));
+    }
+
+    #[test]
+    fn palette_index_picks_closest_colour() {
+        assert_eq!(palette_index(BLACK), 16);
+        assert_eq!(palette_index(WHITE), 231);
+        assert_eq!(palette_index(RED), 196);
+    }
+}
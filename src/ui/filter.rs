@@ -0,0 +1,181 @@
+use cursive::event::Key;
+use cursive::traits::Boxable;
+use cursive::traits::Identifiable;
+use cursive::views::*;
+use cursive::Cursive;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rspotify::spotify::model::track::FullTrack;
+
+/// Returns whether every word of the query can be found in the track's name,
+/// artists or album. Matching ignores case and is fuzzy, i.e. the characters
+/// of a word have to appear in order, but not necessarily next to each other.
+pub fn matches(track: &FullTrack, query: &str) -> bool {
+    let fields = [
+        track.name.to_lowercase(),
+        track
+            .artists
+            .iter()
+            .map(|a| a.name.to_lowercase())
+            .collect::<Vec<String>>()
+            .join(", "),
+        track.album.name.to_lowercase(),
+    ];
+    query
+        .to_lowercase()
+        .split_whitespace()
+        .all(|word| fields.iter().any(|field| fuzzy_contains(field, word)))
+}
+
+fn fuzzy_contains(haystack: &str, needle: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+/// Returns the row after `current` for which `is_match` is true, or the one
+/// before it if `forward` is false. The search wraps around at the ends and
+/// ends at `current` itself, so it is returned if it is the only match.
+pub fn next_match<F>(len: usize, current: usize, forward: bool, is_match: F) -> Option<usize>
+where
+    F: Fn(usize) -> bool,
+{
+    (1..=len)
+        .map(|step| {
+            if forward {
+                (current + step) % len
+            } else {
+                (current + len - step) % len
+            }
+        })
+        .find(|&row| is_match(row))
+}
+
+/// The last query of a filter. It is kept once the filter is closed, so <n>
+/// and <N> can still jump to the tracks matching it.
+#[derive(Clone, Default)]
+pub struct LastQuery(Rc<RefCell<String>>);
+
+impl LastQuery {
+    pub fn get(&self) -> String {
+        self.0.borrow().clone()
+    }
+
+    fn set(&self, query: &str) {
+        *self.0.borrow_mut() = query.to_string();
+    }
+}
+
+/// Returns the current query of a filter, which is empty if it isn't open.
+pub fn query(siv: &mut Cursive, field_id: &str) -> String {
+    siv.call_on_id(field_id, |field: &mut EditView| field.get_content().to_string())
+        .unwrap_or_default()
+}
+
+/// Shows a filter input line at the bottom of a vertical layout and focuses
+/// it. `on_change` is called whenever the query changes and should refresh the
+/// list with the id `list_id`, which gets the focus back on <enter>. The
+/// query is kept in `last_query`. <esc> removes the filter again.
+pub fn open<F>(
+    siv: &mut Cursive,
+    layout_id: &str,
+    field_id: &str,
+    list_id: &str,
+    last_query: &LastQuery,
+    on_change: F,
+) where
+    F: 'static + Fn(&mut Cursive, &str),
+{
+    if siv.find_id::<EditView>(field_id).is_none() {
+        let line = input_line(
+            layout_id,
+            field_id,
+            list_id,
+            last_query.clone(),
+            Rc::new(on_change),
+        );
+        siv.call_on_id(layout_id, move |layout: &mut LinearLayout| {
+            layout.add_child(line);
+        });
+    }
+    let _ = siv.focus_id(field_id);
+}
+
+/// Removes the filter input line from the layout, if there is one, and calls
+/// `on_change` with an empty query.
+pub fn close<F>(siv: &mut Cursive, layout_id: &str, field_id: &str, list_id: &str, on_change: &F)
+where
+    F: Fn(&mut Cursive, &str),
+{
+    if siv.find_id::<EditView>(field_id).is_none() {
+        return;
+    }
+    // the input line is always the last child of the layout
+    siv.call_on_id(layout_id, |layout: &mut LinearLayout| {
+        let last = layout.len() - 1;
+        layout.remove_child(last);
+    });
+    let _ = siv.focus_id(list_id);
+    on_change(siv, "");
+}
+
+fn input_line<F>(
+    layout_id: &str,
+    field_id: &str,
+    list_id: &str,
+    last_query: LastQuery,
+    on_change: Rc<F>,
+) -> LinearLayout
+where
+    F: 'static + Fn(&mut Cursive, &str),
+{
+    let field = {
+        let on_change = on_change.clone();
+        let list_id = list_id.to_string();
+        EditView::new()
+            .on_edit(move |siv, query, _| {
+                last_query.set(query);
+                on_change(siv, query)
+            })
+            .on_submit(move |siv, _| {
+                let _ = siv.focus_id(&list_id);
+            })
+            .with_id(field_id)
+    };
+
+    let (layout_id, field_id, list_id) = (
+        layout_id.to_string(),
+        field_id.to_string(),
+        list_id.to_string(),
+    );
+    let field = OnEventView::new(field).on_event(Key::Esc, move |siv| {
+        close(siv, &layout_id, &field_id, &list_id, &*on_change);
+    });
+
+    LinearLayout::horizontal()
+        .child(TextView::new("/"))
+        .child(field.full_width())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_match_wraps_around() {
+        let is_match = |row| row == 1 || row == 3;
+        assert_eq!(next_match(5, 1, true, is_match), Some(3));
+        assert_eq!(next_match(5, 3, true, is_match), Some(1));
+        assert_eq!(next_match(5, 3, false, is_match), Some(1));
+        assert_eq!(next_match(5, 1, false, is_match), Some(3));
+        assert_eq!(next_match(5, 0, false, is_match), Some(3));
+    }
+
+    #[test]
+    fn next_match_stays_on_only_match() {
+        assert_eq!(next_match(3, 1, true, |row| row == 1), Some(1));
+        assert_eq!(next_match(3, 1, false, |row| row == 1), Some(1));
+        assert_eq!(next_match(3, 1, true, |_| false), None);
+        assert_eq!(next_match(0, 0, true, |_| true), None);
+    }
+}
@@ -0,0 +1,101 @@
+use cursive::theme::ColorStyle;
+use cursive::traits::View;
+use cursive::vec::Vec2;
+use cursive::views::Panel;
+use cursive::Printer;
+use std::time::{Duration, Instant};
+
+use crate::filecache::{format_size, FileCache, Usage};
+
+// walking the cache takes a while, so its usage is only updated this often
+const REFRESH: Duration = Duration::from_secs(5);
+
+// width of the names of the settings
+const NAME_WIDTH: usize = 16;
+
+pub struct InfoView {
+    pub view: Panel<Info>,
+}
+
+impl InfoView {
+    pub fn new(cache: Option<FileCache>) -> InfoView {
+        let view = Info { cache, usage: None };
+        InfoView {
+            view: Panel::new(view).title("Info"),
+        }
+    }
+}
+
+/// The settings of the cache and how much of it is used.
+pub struct Info {
+    cache: Option<FileCache>,
+    usage: Option<(Usage, Instant)>,
+}
+
+impl Info {
+    fn lines(&self) -> Vec<(&'static str, String)> {
+        let cache = match self.cache {
+            Some(ref cache) => cache,
+            None => return vec![("Cache", "disabled".to_string())],
+        };
+
+        let mut lines = vec![
+            ("Cache", cache.dir.display().to_string()),
+            (
+                "Credentials",
+                if cache.has_credentials() {
+                    "cached"
+                } else {
+                    "not cached"
+                }
+                .to_string(),
+            ),
+        ];
+        if !cache.audio {
+            lines.push(("Audio files", "not cached".to_string()));
+            return lines;
+        }
+        if let Some((usage, _)) = self.usage {
+            let percent = usage.bytes * 100 / cache.size_limit.max(1);
+            lines.push(("Audio files", usage.files.to_string()));
+            lines.push((
+                "Used",
+                format!(
+                    "{} of {} ({} %)",
+                    format_size(usage.bytes),
+                    format_size(cache.size_limit),
+                    percent
+                ),
+            ));
+        }
+        lines
+    }
+}
+
+impl View for Info {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        for (y, (name, value)) in self.lines().iter().enumerate() {
+            printer.with_color(ColorStyle::secondary(), |printer| {
+                printer.print((0, y), name);
+            });
+            printer.print((NAME_WIDTH, y), value);
+        }
+    }
+
+    fn layout(&mut self, _: Vec2) {
+        let cache = match self.cache {
+            Some(ref cache) if cache.audio => cache,
+            _ => return,
+        };
+        let outdated = self
+            .usage
+            .map_or(true, |(_, updated)| updated.elapsed() >= REFRESH);
+        if outdated {
+            self.usage = Some((cache.usage(), Instant::now()));
+        }
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+}
@@ -0,0 +1,62 @@
+use cursive::event::{Event, EventResult};
+use cursive::traits::View;
+use cursive::vec::Vec2;
+use cursive::view::ViewWrapper;
+use cursive::views::LinearLayout;
+use cursive::wrap_impl;
+
+use crate::ui::statusbar::StatusBar;
+use crate::ui::tabbar::TabBar;
+
+/// A screen: a view between the tab bar and the statusbar.
+pub struct Layout {
+    layout: LinearLayout,
+    height: usize,
+}
+
+// indices of the bars within the layout
+const TABBAR_INDEX: usize = 0;
+const STATUSBAR_INDEX: usize = 2;
+
+impl Layout {
+    pub fn new<V: View + 'static>(tabbar: TabBar, view: V, statusbar: StatusBar) -> Layout {
+        Layout {
+            layout: LinearLayout::vertical()
+                .child(tabbar)
+                .child(view)
+                .child(statusbar),
+            height: 0,
+        }
+    }
+}
+
+impl ViewWrapper for Layout {
+    wrap_impl!(self.layout: LinearLayout);
+
+    fn wrap_layout(&mut self, size: Vec2) {
+        self.height = size.y;
+        self.layout.layout(size);
+    }
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        // the bars never take the focus, which keeps the keys going to the
+        // view, so clicks on them have to be handed over directly
+        if let Event::Mouse {
+            offset, position, ..
+        } = event
+        {
+            let last_row = self.height.saturating_sub(1);
+            let bar = match position.checked_sub(offset) {
+                Some(ref position) if position.y == 0 => Some((TABBAR_INDEX, 0)),
+                Some(ref position) if position.y == last_row => Some((STATUSBAR_INDEX, last_row)),
+                _ => None,
+            };
+            if let Some((index, row)) = bar {
+                if let Some(bar) = self.layout.get_child_mut(index) {
+                    return bar.on_event(event.relativized((0, row)));
+                }
+            }
+        }
+        self.layout.on_event(event)
+    }
+}
@@ -1,4 +1,16 @@
+pub mod album;
+pub mod albums;
+pub mod artist;
+pub mod artists;
+pub mod cover;
+pub mod filter;
+pub mod info;
+pub mod layout;
+pub mod nowplaying;
 pub mod playlist;
 pub mod queue;
+pub mod screens;
 pub mod search;
-pub mod trackbutton;
+pub mod statusbar;
+pub mod tabbar;
+pub mod tracklist;
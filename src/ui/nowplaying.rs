@@ -0,0 +1,271 @@
+use cursive::theme::ColorStyle;
+use cursive::traits::View;
+use cursive::vec::Vec2;
+use cursive::views::Panel;
+use cursive::Printer;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use image::DynamicImage;
+use log::error;
+
+use rspotify::spotify::model::album::FullAlbum;
+use rspotify::spotify::model::track::FullTrack;
+
+use crate::config::CoverMode;
+use crate::queue::Queue;
+use crate::spotify::Spotify;
+use crate::ui::cover::{self, Art, Colors};
+use crate::ui::statusbar::Status;
+use crate::ui::tracklist::{self, format_duration, join_artists, Column};
+
+pub struct NowPlayingView {
+    pub view: Panel<NowPlaying>,
+}
+
+impl NowPlayingView {
+    pub fn new(
+        spotify: Arc<Spotify>,
+        queue: Arc<Mutex<Queue>>,
+        status: Arc<Mutex<Status>>,
+        cover_mode: CoverMode,
+    ) -> NowPlayingView {
+        let view = NowPlaying {
+            spotify,
+            queue,
+            status,
+            album_id: None,
+            album: Arc::new(Mutex::new(None)),
+            cover_mode,
+            colors: Colors::detect(),
+            cover_dir: cover::cache_dir(),
+            cover_url: None,
+            cover: Arc::new(Mutex::new(None)),
+            art: None,
+        };
+        NowPlayingView {
+            view: Panel::new(view).title("Now playing"),
+        }
+    }
+}
+
+// columns of the upcoming tracks
+const QUEUE_COLUMNS: [Column; 3] = [Column::Title, Column::Artist, Column::Duration];
+
+// width of the names of the details
+const NAME_WIDTH: usize = 12;
+
+// height of the progress bar
+const BAR_HEIGHT: usize = 3;
+
+// largest height of the cover, and the space the details need at least to
+// show it next to them
+const COVER_ROWS: usize = 16;
+const MIN_DETAILS_WIDTH: usize = 40;
+
+/// The details of the track that is playing, its progress and the tracks
+/// queued after it.
+pub struct NowPlaying {
+    spotify: Arc<Spotify>,
+    queue: Arc<Mutex<Queue>>,
+    status: Arc<Mutex<Status>>,
+
+    // the album of the track, which tells the release date. It is loaded in
+    // the background once the track is shown, the id is kept to not retry
+    // after an error.
+    album_id: Option<String>,
+    album: Arc<Mutex<Option<FullAlbum>>>,
+
+    cover_mode: CoverMode,
+    colors: Colors,
+    cover_dir: Option<PathBuf>,
+    // url of the cover of the track, which is loaded in the background
+    cover_url: Option<String>,
+    cover: Arc<Mutex<Option<(String, DynamicImage)>>>,
+    // the cover rendered for its url and the size it had
+    art: Option<(String, Vec2, Art)>,
+}
+
+impl NowPlaying {
+    fn details(&self, track: &FullTrack) -> Vec<(&'static str, String)> {
+        let released = match *self.album.lock().unwrap() {
+            Some(ref album) if album.id == track.album.id => album.release_date.clone(),
+            _ => String::new(),
+        };
+        vec![
+            ("Title", track.name.clone()),
+            ("Artists", join_artists(track)),
+            ("Album", track.album.name.clone()),
+            ("Released", released),
+            (
+                "Track",
+                format!("{} on disc {}", track.track_number, track.disc_number),
+            ),
+            ("Duration", format_duration(track.duration_ms)),
+            ("Popularity", track.popularity.to_string()),
+            ("URI", track.uri.clone()),
+        ]
+    }
+
+    fn draw_progress(&self, printer: &Printer<'_, '_>, status: &Status, track: &FullTrack) {
+        let time = format!(
+            "{} / {}",
+            format_duration(status.elapsed_ms()),
+            format_duration(track.duration_ms)
+        );
+        printer.print((0, 0), &time);
+
+        let filled = printer.size.x * status.progress() / 100;
+        for y in 1..BAR_HEIGHT {
+            printer.with_color(ColorStyle::highlight(), |printer| {
+                printer.print_hline((0, y), filled, " ");
+            });
+            printer.with_color(ColorStyle::highlight_inactive(), |printer| {
+                printer.print_hline((filled, y), printer.size.x - filled, " ");
+            });
+        }
+    }
+
+    // returns the size of the cover, which is square and left of the details
+    fn cover_size(&self, size: Vec2) -> Vec2 {
+        let rows = size.y.min(COVER_ROWS);
+        if self.cover_mode == CoverMode::None || size.x < rows * 2 + 2 + MIN_DETAILS_WIDTH {
+            return Vec2::zero();
+        }
+        Vec2::new(rows * 2, rows)
+    }
+
+    // starts loading the cover of the track unless it is already
+    fn load_cover(&mut self, track: &FullTrack) {
+        let url = match cover::pick_image(&track.album.images) {
+            Some(image) => image.url.clone(),
+            None => return,
+        };
+        if self.cover_url.as_ref() == Some(&url) {
+            return;
+        }
+        self.cover_url = Some(url.clone());
+
+        let dir = match self.cover_dir {
+            Some(ref dir) => dir.clone(),
+            None => return,
+        };
+        let cover = self.cover.clone();
+        thread::spawn(move || match cover::load(&dir, &url) {
+            Ok(image) => *cover.lock().unwrap() = Some((url, image)),
+            Err(e) => error!("could not load cover {}: {}", url, e),
+        });
+    }
+
+    // renders the cover once it is loaded, or again once its size changed
+    fn render_cover(&mut self, size: Vec2) {
+        let url = match self.cover_url {
+            Some(ref url) => url.clone(),
+            None => return,
+        };
+        let rendered = match self.art {
+            Some((ref art_url, art_size, _)) => *art_url == url && art_size == size,
+            None => false,
+        };
+        if rendered {
+            return;
+        }
+
+        let cover = self.cover.lock().unwrap();
+        if let Some((ref cover_url, ref image)) = *cover {
+            if *cover_url == url {
+                let art = Art::render(image, size, self.cover_mode);
+                self.art = Some((url, size, art));
+            }
+        }
+    }
+
+    // starts loading the album of the track unless it is already
+    fn load_album(&mut self, track: &FullTrack) {
+        let album_id = track.album.id.clone();
+        if self.album_id.as_ref() == Some(&album_id) {
+            return;
+        }
+        self.album_id = Some(album_id.clone());
+
+        let spotify = self.spotify.clone();
+        let album = self.album.clone();
+        thread::spawn(move || match spotify.album(&album_id) {
+            Ok(loaded) => *album.lock().unwrap() = Some(loaded),
+            Err(e) => error!("could not load album {}: {}", album_id, e),
+        });
+    }
+
+    fn draw_queue(&self, printer: &Printer<'_, '_>) {
+        printer.with_color(ColorStyle::title_primary(), |printer| {
+            printer.print((0, 0), "Up next");
+        });
+
+        let queue = self.queue.lock().unwrap();
+        let rows = printer.size.y.saturating_sub(1);
+        for (y, track) in queue.iter().take(rows).enumerate() {
+            let label = tracklist::track_label(track, &QUEUE_COLUMNS, printer.size.x);
+            printer.print((0, y + 1), &label);
+        }
+    }
+}
+
+impl View for NowPlaying {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        let status = self.status.lock().unwrap();
+        let track = match status.track() {
+            Some(track) => track,
+            None => {
+                printer.print((0, 0), "Nothing is playing");
+                return;
+            }
+        };
+
+        // the cover of the last track stays until the new one is loaded
+        let cover_size = self.cover_size(printer.size);
+        if let Some((_, _, ref art)) = self.art {
+            if cover_size != Vec2::zero() {
+                art.draw(&printer.cropped(cover_size), self.colors);
+            }
+        }
+        let x = if cover_size.x > 0 {
+            cover_size.x + 2
+        } else {
+            0
+        };
+        let printer = printer.offset((x, 0));
+
+        let details = self.details(track);
+        for (y, (name, value)) in details.iter().enumerate() {
+            printer.with_color(ColorStyle::secondary(), |printer| {
+                printer.print((0, y), name);
+            });
+            printer.print((NAME_WIDTH, y), value);
+        }
+
+        let y = details.len() + 1;
+        self.draw_progress(&printer.offset((0, y)), &status, track);
+
+        let y = y + BAR_HEIGHT + 1;
+        self.draw_queue(&printer.offset((0, y)));
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        let track = match self.status.lock().unwrap().track() {
+            Some(track) => track.clone(),
+            None => return,
+        };
+
+        let cover_size = self.cover_size(size);
+        if cover_size != Vec2::zero() {
+            self.load_cover(&track);
+            self.render_cover(cover_size);
+        }
+        self.load_album(&track);
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+}
@@ -1,136 +1,432 @@
 use cursive::direction::Orientation;
+use cursive::event::Key;
 use cursive::traits::Boxable;
 use cursive::traits::Identifiable;
+use cursive::traits::Scrollable;
 use cursive::views::*;
 use cursive::Cursive;
 use cursive_tree_view::{Placement, TreeView};
 use derive_more::Display;
+use failure::Error;
+use std::collections::HashSet;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::thread;
+
+use log::error;
 
 use rspotify::spotify::model::playlist::{PlaylistTrack, SimplifiedPlaylist};
+use rspotify::spotify::model::track::FullTrack;
 
+use crate::config::PlayMode;
 use crate::events::{Event, EventManager};
 use crate::queue::Queue;
 use crate::spotify::Spotify;
+use crate::ui::artist;
+use crate::ui::filter;
+use crate::ui::tracklist::{self, Column, Row, Sort, TreeLayout, TreeRow};
 
 pub struct PlaylistView {
-    pub view: Panel<LinearLayout>,
+    pub view: Panel<IdView<LinearLayout>>,
 }
 
 #[derive(Debug, Display, Clone)]
 pub enum TreeEntry {
-    #[display(fmt = "{}", "_0.name")]
-    Playlist(SimplifiedPlaylist),
-    #[display(
-        fmt = "{} - {}",
-        "_0.track.name",
-        "_0.track.artists.iter().map(|a| a.name.clone()).collect::<Vec<String>>().join(\", \")"
-    )]
-    Track(PlaylistTrack),
+    #[display(fmt = "{}", "_0.playlist.name")]
+    Playlist(PlaylistEntry),
+    // a track along with its position in the playlist, whether it is marked,
+    // whether it is playing and the width of the tree
+    #[display(fmt = "{}", "track_label(_0, *_1, *_2, *_3, *_4)")]
+    Track(PlaylistTrack, usize, bool, bool, usize),
+}
+
+// the track that is playing is kept along with the playlists by
+// `show_playing`, so only the width is taken from the layout
+impl TreeRow for TreeEntry {
+    fn set_layout(&mut self, width: usize, _playing: Option<&str>) {
+        if let TreeEntry::Track(_, _, _, _, ref mut tree_width) = *self {
+            *tree_width = width;
+        }
+    }
+}
+
+/// A playlist in the tree. Its tracks are kept once they have been loaded, so
+/// the rows can be filtered and sorted without fetching them again.
+#[derive(Debug, Clone)]
+pub struct PlaylistEntry {
+    playlist: SimplifiedPlaylist,
+    tracks: Option<Vec<PlaylistTrack>>,
+    sort: Option<Sort>,
+    // positions of the marked tracks and of the start of a range that is
+    // being marked
+    marked: HashSet<usize>,
+    anchor: Option<usize>,
+    // id of the track that is playing, to mark it among the tracks
+    playing: Option<String>,
+}
+
+impl PlaylistEntry {
+    fn new(playlist: SimplifiedPlaylist, playing: Option<String>) -> PlaylistEntry {
+        PlaylistEntry {
+            playlist,
+            tracks: None,
+            sort: None,
+            marked: HashSet::new(),
+            anchor: None,
+            playing,
+        }
+    }
+
+    fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.anchor = None;
+    }
 }
 
+// columns of the track rows
+const COLUMNS: [Column; 5] = [
+    Column::Title,
+    Column::Artist,
+    Column::Album,
+    Column::AddedAt,
+    Column::Duration,
+];
+
 const TREE_ID: &str = "playlist_tree";
+const LAYOUT_ID: &str = "playlist_layout";
+const FILTER_ID: &str = "playlist_filter";
 
 impl PlaylistView {
     pub fn new(
         spotify: Arc<Spotify>,
         queue: Arc<Mutex<Queue>>,
         event_manager: EventManager,
+        play_mode: PlayMode,
     ) -> PlaylistView {
         let spotify = spotify.clone();
 
         let mut tree_view = TreeView::new();
 
-        if let Ok(playlist_page) = spotify.api.current_user_playlists(None, None) {
-            for (i, playlist) in playlist_page.items.into_iter().enumerate() {
-                tree_view.insert_container_item(TreeEntry::Playlist(playlist), Placement::After, i);
+        // the playlists of the last run are shown until they have been
+        // fetched again in the background
+        let playlists = match spotify.cached_playlists() {
+            Some(playlists) => {
+                let spotify = spotify.clone();
+                let event_manager = event_manager.clone();
+                thread::spawn(move || match spotify.current_user_playlists() {
+                    Ok(playlists) => event_manager.send(Event::PlaylistsLoaded(playlists)),
+                    Err(e) => error!("could not load playlists: {}", e),
+                });
+                playlists
             }
+            None => spotify.current_user_playlists().unwrap_or_else(|e| {
+                error!("could not load playlists: {}", e);
+                Vec::new()
+            }),
+        };
+        for (i, playlist) in playlists.into_iter().enumerate() {
+            tree_view.insert_container_item(
+                TreeEntry::Playlist(PlaylistEntry::new(playlist, spotify.current_track_id())),
+                Placement::After,
+                i,
+            );
         }
 
         {
-            let _queue = queue.clone();
-            let _spotify = spotify.clone();
+            let queue = queue.clone();
+            let event_manager = event_manager.clone();
             tree_view.set_on_submit(move |siv: &mut Cursive, row: usize| {
                 siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
-                    if let Some(&TreeEntry::Track(ref playlist_track)) = tree_view.borrow_item(row)
+                    if let Some(&TreeEntry::Track(ref playlist_track, position, ..)) =
+                        tree_view.borrow_item(row)
                     {
                         event_manager.send(Event::Play(playlist_track.track.clone()));
 
-                        // // TODO send event to play instead.
-                        // let trackid = SpotifyId::from_base62(&playlist_track.track.id)
-                        //     .expect("could not load track");
-                        // spotify.load(trackid);
-                        // spotify.play();
-
-                        // let mut queue = queue.lock().unwrap();
-                        // queue.enqueue(playlist_track.track.clone());
+                        // in context mode the rest of the playlist replaces the
+                        // queue, including the tracks hidden by a filter
+                        if play_mode == PlayMode::Context {
+                            let parent = parent_row(tree_view, row);
+                            if let Some(tracks) =
+                                parent.and_then(|parent| loaded(tree_view, parent))
+                            {
+                                let tracks = tracks[position + 1..]
+                                    .iter()
+                                    .map(|playlist_track| playlist_track.track.clone())
+                                    .collect();
+                                queue.lock().unwrap().replace(tracks);
+                            }
+                        }
                     }
                 });
             });
         }
 
-        tree_view.set_on_collapse(move |siv: &mut Cursive, row, is_collapsed, children| {
-            if !is_collapsed && children == 0 {
-                siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
-                    let track_page = if let Some(&TreeEntry::Playlist(ref playlist)) =
-                        tree_view.borrow_item(row)
-                    {
-                        spotify
-                            .api
-                            .user_playlist_tracks(
-                                &playlist.owner.id,
-                                &playlist.id,
-                                None,
-                                100,
-                                0,
-                                None,
-                            )
-                            .ok()
-                    } else {
-                        None
+        {
+            let spotify = spotify.clone();
+            tree_view.set_on_collapse(move |siv: &mut Cursive, row, is_collapsed, children| {
+                if !is_collapsed && children == 0 {
+                    let query = filter::query(siv, FILTER_ID);
+                    siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+                        if let Some(&mut TreeEntry::Playlist(ref mut entry)) =
+                            tree_view.borrow_item_mut(row)
+                        {
+                            if entry.tracks.is_none() {
+                                entry.tracks = Some(load_tracks(&spotify, &entry.playlist));
+                            }
+                        }
+                        fill(tree_view, row, &query);
+                    });
+                }
+            });
+        }
+
+        // <a> queues the marked tracks or the focused entry
+        let view = {
+            let queue = queue.clone();
+            let spotify = spotify.clone();
+            OnEventView::new(TreeLayout::new(tree_view.with_id(TREE_ID))).on_event('a', move |siv: &mut Cursive| {
+                let mut tracks = target_tracks(siv);
+
+                // the focused playlist may not have been expanded yet, in
+                // which case its tracks are fetched first
+                if tracks.is_empty() {
+                    if let Some(entry) = focused_playlist(siv) {
+                        let loaded = match entry.tracks {
+                            Some(tracks) => tracks,
+                            None => load_tracks(&spotify, &entry.playlist),
+                        };
+                        tracks = loaded
+                            .into_iter()
+                            .map(|playlist_track| playlist_track.track)
+                            .collect();
+                    }
+                }
+
+                let mut queue = queue.lock().unwrap();
+                for track in tracks {
+                    queue.enqueue(track);
+                }
+            })
+        };
+
+        // <R> replaces the queue with the focused playlist and starts playing it
+        let view = {
+            let queue = queue.clone();
+            let spotify = spotify.clone();
+            let event_manager = event_manager.clone();
+            view.on_event('R', move |siv: &mut Cursive| {
+                if let Some(entry) = focused_playlist(siv) {
+                    let loaded = match entry.tracks {
+                        Some(tracks) => tracks,
+                        None => load_tracks(&spotify, &entry.playlist),
                     };
-                    for playlist_track in track_page.into_iter().flat_map(|t| t.items.into_iter()) {
-                        tree_view.insert_item(
-                            TreeEntry::Track(playlist_track),
-                            Placement::LastChild,
-                            row,
-                        );
+                    let mut tracks = loaded
+                        .into_iter()
+                        .map(|playlist_track| playlist_track.track);
+                    if let Some(first) = tracks.next() {
+                        event_manager.send(Event::Play(first));
+                        queue.lock().unwrap().replace(tracks.collect());
+                    }
+                }
+            })
+        };
+
+        // <p> adds the marked tracks or the focused one to another playlist
+        let view = {
+            let spotify = spotify.clone();
+            let event_manager = event_manager.clone();
+            view.on_event('p', move |siv: &mut Cursive| {
+                let tracks = target_tracks(siv);
+                if !tracks.is_empty() {
+                    add_to_playlist(siv, spotify.clone(), event_manager.clone(), tracks);
+                }
+            })
+        };
+
+        // <s> saves the marked tracks or the focused one as a new playlist
+        let view = {
+            let spotify = spotify.clone();
+            let event_manager = event_manager.clone();
+            view.on_event('s', move |siv: &mut Cursive| {
+                let tracks = target_tracks(siv);
+                if !tracks.is_empty() {
+                    save_as_playlist(siv, spotify.clone(), event_manager.clone(), tracks);
+                }
+            })
+        };
+
+        // <d> removes the marked tracks or the focused one from their
+        // playlist, or deletes the focused playlist after asking for
+        // confirmation
+        let view = {
+            let spotify = spotify.clone();
+            view.on_event('d', move |siv: &mut Cursive| {
+                let has_targets = siv
+                    .call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+                        targets(tree_view).map_or(false, |(_, positions)| !positions.is_empty())
+                    })
+                    .unwrap_or(false);
+                if !has_targets {
+                    if let Some(entry) = focused_playlist(siv) {
+                        PlaylistView::confirm_delete(siv, spotify.clone(), entry.playlist);
                     }
+                    return;
+                }
+
+                let query = filter::query(siv, FILTER_ID);
+                let result = siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+                    PlaylistView::remove_tracks(&spotify, tree_view, &query)
                 });
-            }
-        });
+                if let Some(Err(e)) = result {
+                    report_error(siv, "could not remove tracks", e);
+                }
+            })
+        };
 
+        // <space> marks the focused track, <v> marks the range of tracks
+        // between the two rows it is pressed on
+        let view = view
+            .on_event(' ', |siv: &mut Cursive| {
+                let query = filter::query(siv, FILTER_ID);
+                siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+                    toggle_mark(tree_view, &query);
+                });
+            })
+            .on_event('v', |siv: &mut Cursive| {
+                let query = filter::query(siv, FILTER_ID);
+                siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+                    toggle_range(tree_view, &query);
+                });
+            });
+
+        // <c> creates a new, empty playlist
         let view = {
-            let queue = queue.clone();
-            OnEventView::new(tree_view.with_id(TREE_ID)).on_event('a', move |siv: &mut Cursive| {
+            let spotify = spotify.clone();
+            let event_manager = event_manager.clone();
+            view.on_event('c', move |siv: &mut Cursive| {
+                save_as_playlist(siv, spotify.clone(), event_manager.clone(), Vec::new());
+            })
+        };
+
+        // <n> and <N> jump to the next and previous track matching the last
+        // query, like in the other lists. Only the tracks of expanded
+        // playlists are searched, the others aren't loaded.
+        let last_query = filter::LastQuery::default();
+        let view = {
+            let last_query = last_query.clone();
+            view.on_event('n', move |siv: &mut Cursive| {
+                jump_to_match(siv, &last_query.get(), true);
+            })
+        };
+        let view = {
+            let last_query = last_query.clone();
+            view.on_event('N', move |siv: &mut Cursive| {
+                jump_to_match(siv, &last_query.get(), false);
+            })
+        };
+
+        // <r> renames the focused playlist
+        let view = {
+            let spotify = spotify.clone();
+            view.on_event('r', move |siv: &mut Cursive| {
+                if let Some(entry) = focused_playlist(siv) {
+                    PlaylistView::rename(siv, spotify.clone(), entry.playlist);
+                }
+            })
+        };
+
+        // <J> and <K> move the focused track down and up within its playlist
+        let view = {
+            let spotify = spotify.clone();
+            view.on_event('J', move |siv: &mut Cursive| {
+                let query = filter::query(siv, FILTER_ID);
+                let result = siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+                    PlaylistView::move_track(&spotify, tree_view, false, &query)
+                });
+                if let Some(Err(e)) = result {
+                    report_error(siv, "could not move track", e);
+                }
+            })
+        };
+        let view = {
+            let spotify = spotify.clone();
+            view.on_event('K', move |siv: &mut Cursive| {
+                let query = filter::query(siv, FILTER_ID);
+                let result = siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+                    PlaylistView::move_track(&spotify, tree_view, true, &query)
+                });
+                if let Some(Err(e)) = result {
+                    report_error(siv, "could not move track", e);
+                }
+            })
+        };
+
+        // <1> to <5> sort the tracks of a playlist by the respective column,
+        // pressing the same key again reverses and then resets the order
+        let mut view = view;
+        for (i, &column) in COLUMNS.iter().enumerate() {
+            let key = (b'1' + i as u8) as char;
+            view.set_on_event(key, move |siv: &mut Cursive| {
+                let query = filter::query(siv, FILTER_ID);
                 siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
-                    if let Some(focused_row) = tree_view.row() {
-                        match tree_view.borrow_item(focused_row) {
-                            Some(&TreeEntry::Playlist(_)) => {
-                                let mut idx = focused_row + 1;
-                                let mut queue = queue.lock().unwrap();
-                                while let Some(&TreeEntry::Track(ref playlist_track)) =
-                                    tree_view.borrow_item(idx)
-                                {
-                                    queue.enqueue(playlist_track.track.clone());
-                                    idx += 1;
-                                }
-                            }
-                            Some(&TreeEntry::Track(ref playlist_track)) => {
-                                let mut queue = queue.lock().unwrap();
-                                queue.enqueue(playlist_track.track.clone());
-                            }
-                            None => (),
-                        }
-                    }
+                    sort_playlist(tree_view, column, &query);
                 });
+            });
+        }
+
+        // </> filters the tracks of the expanded playlists, collapsed ones are
+        // filtered once they are expanded. <esc> removes the marks or, if there
+        // are none, the filter.
+        let view = view
+            .on_event('/', move |siv: &mut Cursive| {
+                filter::open(
+                    siv,
+                    LAYOUT_ID,
+                    FILTER_ID,
+                    TREE_ID,
+                    &last_query,
+                    apply_filter,
+                );
+            })
+            .on_event(Key::Esc, |siv: &mut Cursive| {
+                let query = filter::query(siv, FILTER_ID);
+                let cleared = siv
+                    .call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+                        clear_all_marks(tree_view, &query)
+                    })
+                    .unwrap_or(false);
+                if !cleared {
+                    filter::close(siv, LAYOUT_ID, FILTER_ID, TREE_ID, &apply_filter);
+                }
+            });
+
+        // <o> jumps to the track that is playing
+        let view = view.on_event('o', |siv: &mut Cursive| {
+            siv.call_on_id(TREE_ID, jump_to_playing);
+        });
+
+        // <L> shows the album of the focused track
+        let view = {
+            let event_manager = event_manager.clone();
+            view.on_event('L', move |siv: &mut Cursive| {
+                if let Some(track) = focused_track(siv) {
+                    event_manager.send(Event::ShowAlbum(track.album.id));
+                }
             })
         };
 
+        // <A> shows the artist of the focused track
+        let view = view.on_event('A', move |siv: &mut Cursive| {
+            if let Some(track) = focused_track(siv) {
+                artist::select_artist(siv, &event_manager, &track.artists);
+            }
+        });
+
         let layout = LinearLayout::new(Orientation::Vertical)
             // .child(searchfield)
-            .child(view.full_width());
+            .child(view.full_width())
+            .with_id(LAYOUT_ID);
 
         //         let searchfield = EditView::new()
         //             .on_submit(move |s, input| {
@@ -145,4 +441,751 @@ impl PlaylistView {
         let rootpanel = Panel::new(layout).title("Playlists");
         PlaylistView { view: rootpanel }
     }
+
+    /// Reloads the list of playlists, e.g. after one has been created. This
+    /// only has an effect while the playlist screen is active.
+    pub fn reload_playlists(siv: &mut Cursive, spotify: &Spotify) {
+        let playlists = match spotify.current_user_playlists() {
+            Ok(playlists) => playlists,
+            Err(e) => {
+                error!("could not load playlists: {}", e);
+                return;
+            }
+        };
+        PlaylistView::set_playlists(siv, spotify, playlists);
+    }
+
+    /// Replaces the list of playlists. Playlists whose snapshot didn't change
+    /// keep their tracks, marks and sort order, and stay expanded. This only
+    /// has an effect while the playlist screen is active.
+    pub fn set_playlists(siv: &mut Cursive, spotify: &Spotify, playlists: Vec<SimplifiedPlaylist>) {
+        let query = filter::query(siv, FILTER_ID);
+        siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+            let selected = tree_view.row();
+
+            // the entries of the playlists, and whether they were expanded
+            let mut previous = Vec::new();
+            let mut row = 0;
+            while row < tree_view.len() {
+                let entry = match tree_view.borrow_item(row) {
+                    Some(&TreeEntry::Playlist(ref entry)) => Some(entry.clone()),
+                    _ => None,
+                };
+                if let Some(entry) = entry {
+                    previous.push((entry, is_expanded(tree_view, row)));
+                }
+                row += 1;
+            }
+
+            tree_view.clear();
+            let mut expanded = Vec::new();
+            for (i, playlist) in playlists.into_iter().enumerate() {
+                let kept = previous.iter().find(|(entry, _)| {
+                    entry.playlist.id == playlist.id
+                        && entry.playlist.snapshot_id == playlist.snapshot_id
+                });
+                let entry = match kept {
+                    Some((entry, was_expanded)) => {
+                        if *was_expanded {
+                            expanded.push(i);
+                        }
+                        PlaylistEntry {
+                            playlist,
+                            ..entry.clone()
+                        }
+                    }
+                    None => PlaylistEntry::new(playlist, spotify.current_track_id()),
+                };
+                tree_view.insert_container_item(TreeEntry::Playlist(entry), Placement::After, i);
+            }
+
+            // expanding a playlist moves the rows below it, so the last one
+            // goes first
+            for row in expanded.into_iter().rev() {
+                fill(tree_view, row, &query);
+                tree_view.set_collapsed(row, false);
+            }
+
+            if let Some(selected) = selected {
+                if !tree_view.is_empty() {
+                    tree_view.set_selected_row(selected.min(tree_view.len() - 1));
+                }
+            }
+        });
+    }
+
+    /// Marks the track that is playing. This only has an effect while the
+    /// playlist screen is active.
+    pub fn show_playing(siv: &mut Cursive, track: Option<&FullTrack>) {
+        let query = filter::query(siv, FILTER_ID);
+        let playing = track.map(|track| track.id.clone());
+        siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+            let selected = tree_view.row();
+
+            // filling a playlist only changes the rows below it, so the rows
+            // can be walked while doing so
+            let mut row = 0;
+            while row < tree_view.len() {
+                if let Some(&mut TreeEntry::Playlist(ref mut entry)) =
+                    tree_view.borrow_item_mut(row)
+                {
+                    entry.playing = playing.clone();
+                }
+                fill(tree_view, row, &query);
+                row += 1;
+            }
+
+            // the rows stay the same, only their labels change
+            if let Some(selected) = selected {
+                tree_view.set_selected_row(selected);
+            }
+        });
+    }
+
+    /// Reloads the tracks of a playlist after it has been modified elsewhere.
+    /// This only has an effect while the playlist screen is active.
+    pub fn reload_playlist(siv: &mut Cursive, spotify: &Spotify, id: &str, snapshot_id: &str) {
+        let query = filter::query(siv, FILTER_ID);
+        siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+            let row = match find_playlist(tree_view, id) {
+                Some(row) => row,
+                None => return,
+            };
+
+            // playlists that haven't been expanded yet fetch their tracks
+            // once they are
+            if let Some(&mut TreeEntry::Playlist(ref mut entry)) = tree_view.borrow_item_mut(row) {
+                entry.playlist.snapshot_id = snapshot_id.to_string();
+                if entry.tracks.is_some() {
+                    entry.tracks = Some(load_tracks(spotify, &entry.playlist));
+                    entry.clear_marks();
+                }
+            }
+            fill(tree_view, row, &query);
+        });
+    }
+
+    fn confirm_delete(siv: &mut Cursive, spotify: Arc<Spotify>, playlist: SimplifiedPlaylist) {
+        // deleting unfollows the playlist, which only deletes it for its
+        // owner and removes it from the library of everyone else
+        let (title, message, button, error) = if playlist.owner.id == spotify.user {
+            (
+                "Delete playlist",
+                format!("Delete playlist {}?", playlist.name),
+                "Delete",
+                "could not delete playlist",
+            )
+        } else {
+            let owner = playlist
+                .owner
+                .display_name
+                .clone()
+                .unwrap_or_else(|| playlist.owner.id.clone());
+            (
+                "Unfollow playlist",
+                format!(
+                    "Playlist {} is owned by {}. Remove it from your library?",
+                    playlist.name, owner
+                ),
+                "Unfollow",
+                "could not unfollow playlist",
+            )
+        };
+        let dialog = Dialog::text(message)
+            .title(title)
+            .button(button, move |siv| {
+                siv.pop_layer();
+                let result = siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+                    spotify.playlist_delete(&playlist).map(|_| {
+                        if let Some(row) = find_playlist(tree_view, &playlist.id) {
+                            tree_view.remove_item(row);
+                        }
+                    })
+                });
+                if let Some(Err(e)) = result {
+                    report_error(siv, error, e);
+                }
+            })
+            .dismiss_button("Cancel");
+        siv.add_layer(dialog);
+    }
+
+    fn rename(siv: &mut Cursive, spotify: Arc<Spotify>, playlist: SimplifiedPlaylist) {
+        if playlist.owner.id != spotify.user {
+            siv.add_layer(Dialog::info("Only your own playlists can be renamed."));
+            return;
+        }
+
+        let current_name = playlist.name.clone();
+        let dialog = name_dialog("Rename playlist", &current_name, move |siv, name| {
+            let result = siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+                spotify.playlist_rename(&playlist, name).map(|_| {
+                    let row = find_playlist(tree_view, &playlist.id);
+                    if let Some(&mut TreeEntry::Playlist(ref mut entry)) =
+                        row.and_then(|row| tree_view.borrow_item_mut(row))
+                    {
+                        entry.playlist.name = name.to_string();
+                    }
+                })
+            });
+            if let Some(Err(e)) = result {
+                report_error(siv, "could not rename playlist", e);
+            }
+        });
+        siv.add_layer(dialog);
+    }
+
+    // stores the modified tracks of the playlist at `row` and recreates its
+    // rows, the marks refer to the old positions and are removed
+    fn set_tracks(
+        tree_view: &mut TreeView<TreeEntry>,
+        row: usize,
+        snapshot_id: String,
+        tracks: Vec<PlaylistTrack>,
+        query: &str,
+    ) {
+        if let Some(&mut TreeEntry::Playlist(ref mut entry)) = tree_view.borrow_item_mut(row) {
+            entry.playlist.snapshot_id = snapshot_id;
+            entry.tracks = Some(tracks);
+            entry.clear_marks();
+        }
+        fill(tree_view, row, query);
+    }
+
+    fn remove_tracks(
+        spotify: &Spotify,
+        tree_view: &mut TreeView<TreeEntry>,
+        query: &str,
+    ) -> Result<(), Error> {
+        let (parent, positions) = match targets(tree_view) {
+            Some(targets) => targets,
+            None => return Ok(()),
+        };
+        let (playlist, mut tracks) = match tree_view.borrow_item(parent) {
+            Some(&TreeEntry::Playlist(PlaylistEntry {
+                ref playlist,
+                tracks: Some(ref tracks),
+                ..
+            })) => (playlist.clone(), tracks.clone()),
+            _ => return Ok(()),
+        };
+        let row = tree_view.row().unwrap_or(parent);
+
+        let occurrences: Vec<(&FullTrack, usize)> = positions
+            .iter()
+            .map(|&position| (&tracks[position].track, position))
+            .collect();
+        let snapshot_id = spotify.playlist_remove_tracks(&playlist, &occurrences)?;
+
+        // the positions are ascending, removing from the end keeps the
+        // remaining ones valid
+        for &position in positions.iter().rev() {
+            tracks.remove(position);
+        }
+        PlaylistView::set_tracks(tree_view, parent, snapshot_id, tracks, query);
+
+        if !tree_view.is_empty() {
+            tree_view.set_selected_row(row.min(tree_view.len() - 1));
+        }
+        Ok(())
+    }
+
+    fn move_track(
+        spotify: &Spotify,
+        tree_view: &mut TreeView<TreeEntry>,
+        up: bool,
+        query: &str,
+    ) -> Result<(), Error> {
+        let row = match tree_view.row() {
+            Some(row) => row,
+            None => return Ok(()),
+        };
+        let position = match tree_view.borrow_item(row) {
+            Some(&TreeEntry::Track(_, position, ..)) => position,
+            _ => return Ok(()),
+        };
+        let parent = match parent_row(tree_view, row) {
+            Some(parent) => parent,
+            None => return Ok(()),
+        };
+        let (playlist, mut tracks) = match tree_view.borrow_item(parent) {
+            Some(&TreeEntry::Playlist(PlaylistEntry {
+                ref playlist,
+                tracks: Some(ref tracks),
+                ..
+            })) => (playlist.clone(), tracks.clone()),
+            _ => return Ok(()),
+        };
+
+        // tracks are moved within the whole playlist, even if their
+        // neighbours are hidden by a filter
+        let target = if up {
+            if position == 0 {
+                return Ok(());
+            }
+            position - 1
+        } else {
+            if position + 1 >= tracks.len() {
+                return Ok(());
+            }
+            position + 1
+        };
+
+        let insert_before = if up { position - 1 } else { position + 2 };
+        let snapshot_id = spotify.playlist_move_track(&playlist, position, insert_before)?;
+        tracks.swap(position, target);
+        PlaylistView::set_tracks(tree_view, parent, snapshot_id, tracks, query);
+
+        if let Some(moved) = track_row(tree_view, parent, target) {
+            tree_view.set_selected_row(moved);
+        }
+        Ok(())
+    }
+}
+
+// recreates the rows of an expanded playlist from its loaded tracks, leaving
+// out those that don't match the query
+fn fill(tree_view: &mut TreeView<TreeEntry>, row: usize, query: &str) {
+    let (tracks, sort, marked, playing) = match tree_view.borrow_item(row) {
+        Some(&TreeEntry::Playlist(PlaylistEntry {
+            tracks: Some(ref tracks),
+            sort,
+            ref marked,
+            ref playing,
+            ..
+        })) => (tracks.clone(), sort, marked.clone(), playing.clone()),
+        _ => return,
+    };
+
+    let mut rows: Vec<Row> = tracks
+        .iter()
+        .enumerate()
+        .filter(|&(_, playlist_track)| filter::matches(&playlist_track.track, query))
+        .map(|(position, playlist_track)| to_row(playlist_track, position))
+        .collect();
+    if let Some(sort) = sort {
+        sort.apply(&mut rows);
+    }
+
+    tree_view.remove_children(row);
+    for track_row in rows {
+        let position = track_row.id;
+        let is_playing = playing.as_ref() == Some(&track_row.track.id);
+        tree_view.insert_item(
+            TreeEntry::Track(
+                tracks[position].clone(),
+                position,
+                marked.contains(&position),
+                is_playing,
+                0,
+            ),
+            Placement::LastChild,
+            row,
+        );
+    }
+}
+
+// sorts the tracks of the focused playlist, or of the playlist containing the
+// focused track
+fn sort_playlist(tree_view: &mut TreeView<TreeEntry>, column: Column, query: &str) {
+    if let Some(parent) = focused_parent(tree_view) {
+        if let Some(&mut TreeEntry::Playlist(ref mut entry)) = tree_view.borrow_item_mut(parent) {
+            entry.sort = Sort::toggle(entry.sort, column);
+            // the range refers to the rows as they were shown
+            entry.anchor = None;
+        }
+        fill(tree_view, parent, query);
+        tree_view.set_selected_row(parent);
+    }
+}
+
+// marks the focused track, or removes its mark, and moves on to the next one
+fn toggle_mark(tree_view: &mut TreeView<TreeEntry>, query: &str) {
+    let (row, parent, position) = match focused_track_row(tree_view) {
+        Some(focused) => focused,
+        None => return,
+    };
+
+    if let Some(&mut TreeEntry::Playlist(ref mut entry)) = tree_view.borrow_item_mut(parent) {
+        if !entry.marked.remove(&position) {
+            entry.marked.insert(position);
+        }
+    }
+    fill(tree_view, parent, query);
+
+    let next = match tree_view.borrow_item(row + 1) {
+        Some(&TreeEntry::Track(..)) => row + 1,
+        _ => row,
+    };
+    tree_view.set_selected_row(next);
+}
+
+// starts a range at the focused track, or marks all tracks shown between the
+// start of the range and the focused track
+fn toggle_range(tree_view: &mut TreeView<TreeEntry>, query: &str) {
+    let (row, parent, position) = match focused_track_row(tree_view) {
+        Some(focused) => focused,
+        None => return,
+    };
+
+    let anchor = match tree_view.borrow_item_mut(parent) {
+        Some(&mut TreeEntry::Playlist(ref mut entry)) => entry.anchor.take(),
+        _ => return,
+    };
+    let start = anchor.and_then(|anchor| track_row(tree_view, parent, anchor));
+
+    let range: Vec<usize> = match start {
+        Some(start) => (start.min(row)..=start.max(row))
+            .filter_map(|row| match tree_view.borrow_item(row) {
+                Some(&TreeEntry::Track(_, position, ..)) => Some(position),
+                _ => None,
+            })
+            .collect(),
+        None => vec![position],
+    };
+
+    if let Some(&mut TreeEntry::Playlist(ref mut entry)) = tree_view.borrow_item_mut(parent) {
+        entry.marked.extend(range);
+        if start.is_none() {
+            entry.anchor = Some(position);
+        }
+    }
+    fill(tree_view, parent, query);
+    tree_view.set_selected_row(row);
+}
+
+// removes the marks of all playlists, returns whether there were any
+fn clear_all_marks(tree_view: &mut TreeView<TreeEntry>, query: &str) -> bool {
+    let mut cleared = false;
+    for row in 0..tree_view.len() {
+        let marked = match tree_view.borrow_item_mut(row) {
+            Some(&mut TreeEntry::Playlist(ref mut entry)) => {
+                let marked = !entry.marked.is_empty() || entry.anchor.is_some();
+                entry.clear_marks();
+                marked
+            }
+            _ => false,
+        };
+        if marked {
+            fill(tree_view, row, query);
+            cleared = true;
+        }
+    }
+    cleared
+}
+
+// returns the row of the focused playlist, or of the playlist containing the
+// focused track
+fn focused_parent(tree_view: &mut TreeView<TreeEntry>) -> Option<usize> {
+    let row = tree_view.row()?;
+    match tree_view.borrow_item(row) {
+        Some(&TreeEntry::Track(..)) => parent_row(tree_view, row),
+        _ => Some(row),
+    }
+}
+
+// returns the row of the focused track, the row of its playlist and the
+// position of the track within the playlist
+fn focused_track_row(tree_view: &mut TreeView<TreeEntry>) -> Option<(usize, usize, usize)> {
+    let row = tree_view.row()?;
+    let position = match tree_view.borrow_item(row) {
+        Some(&TreeEntry::Track(_, position, ..)) => position,
+        _ => return None,
+    };
+    parent_row(tree_view, row).map(|parent| (row, parent, position))
+}
+
+// returns the row of the playlist containing the track at `row`, which is the
+// closest playlist above it as tracks are only ever shown below their playlist
+fn parent_row(tree_view: &mut TreeView<TreeEntry>, row: usize) -> Option<usize> {
+    (0..row)
+        .rev()
+        .find(|&row| match tree_view.borrow_item(row) {
+            Some(&TreeEntry::Playlist(..)) => true,
+            _ => false,
+        })
+}
+
+// returns whether the playlist at `row` is expanded, which is told by the
+// row below it showing one of its tracks
+fn is_expanded(tree_view: &mut TreeView<TreeEntry>, row: usize) -> bool {
+    match tree_view.borrow_item(row + 1) {
+        Some(&TreeEntry::Track(..)) => true,
+        _ => false,
+    }
+}
+
+// returns the row showing the track at `position` of the playlist at `parent`
+fn track_row(tree_view: &mut TreeView<TreeEntry>, parent: usize, position: usize) -> Option<usize> {
+    for row in parent + 1..tree_view.len() {
+        match tree_view.borrow_item(row) {
+            Some(&TreeEntry::Track(_, p, ..)) if p == position => return Some(row),
+            Some(&TreeEntry::Track(..)) => (),
+            _ => return None,
+        }
+    }
+    None
+}
+
+// returns the row of the playlist the focus is in, along with the ascending
+// positions of its marked tracks, or of the focused track if none are marked
+fn targets(tree_view: &mut TreeView<TreeEntry>) -> Option<(usize, Vec<usize>)> {
+    let parent = focused_parent(tree_view)?;
+    let mut positions: Vec<usize> = match tree_view.borrow_item(parent) {
+        Some(&TreeEntry::Playlist(ref entry)) => entry.marked.iter().cloned().collect(),
+        _ => return None,
+    };
+    if positions.is_empty() {
+        if let Some((_, _, position)) = focused_track_row(tree_view) {
+            positions.push(position);
+        }
+    }
+    positions.sort();
+    Some((parent, positions))
+}
+
+// returns the tracks an action applies to and removes the marks, as the
+// tracks are about to be acted upon
+fn target_tracks(siv: &mut Cursive) -> Vec<FullTrack> {
+    let query = filter::query(siv, FILTER_ID);
+    siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+        let (parent, positions) = match targets(tree_view) {
+            Some(targets) => targets,
+            None => return Vec::new(),
+        };
+        let tracks: Vec<FullTrack> = match loaded(tree_view, parent) {
+            Some(tracks) => positions
+                .iter()
+                .map(|&position| tracks[position].track.clone())
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let row = tree_view.row();
+        clear_all_marks(tree_view, &query);
+        if let Some(row) = row {
+            tree_view.set_selected_row(row);
+        }
+        tracks
+    })
+    .unwrap_or_default()
+}
+
+// returns the loaded tracks of the playlist at `row`
+fn loaded(tree_view: &mut TreeView<TreeEntry>, row: usize) -> Option<&Vec<PlaylistTrack>> {
+    match tree_view.borrow_item(row) {
+        Some(&TreeEntry::Playlist(PlaylistEntry {
+            tracks: Some(ref tracks),
+            ..
+        })) => Some(tracks),
+        _ => None,
+    }
+}
+
+fn to_row(playlist_track: &PlaylistTrack, position: usize) -> Row {
+    Row {
+        id: position,
+        track: playlist_track.track.clone(),
+        added_at: Some(playlist_track.added_at.format("%Y-%m-%d").to_string()),
+    }
+}
+
+fn track_label(
+    playlist_track: &PlaylistTrack,
+    position: usize,
+    marked: bool,
+    playing: bool,
+    tree_width: usize,
+) -> String {
+    // the mark and the marker take up three columns
+    let width = tracklist::tree_label_width(tree_width, 1).saturating_sub(3);
+    let label = tracklist::label(&to_row(playlist_track, position), &COLUMNS, width);
+    let mark = if marked { "+" } else { " " };
+    let marker = if playing {
+        tracklist::PLAYING_MARKER
+    } else {
+        " "
+    };
+    format!("{}{} {}", mark, marker, label)
+}
+
+fn apply_filter(siv: &mut Cursive, query: &str) {
+    siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+        // filling a playlist only changes the rows below it, so the rows can
+        // be walked while doing so
+        let mut row = 0;
+        while row < tree_view.len() {
+            if let Some(&TreeEntry::Playlist(..)) = tree_view.borrow_item(row) {
+                fill(tree_view, row, query);
+            }
+            row += 1;
+        }
+    });
+}
+
+// moves the focus to the next or previous track that matches the query,
+// skipping the playlist rows
+fn jump_to_match(siv: &mut Cursive, query: &str, forward: bool) {
+    if query.is_empty() {
+        return;
+    }
+    siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+        let current = tree_view.row().unwrap_or(0);
+        let matches: Vec<bool> = (0..tree_view.len())
+            .map(|row| match tree_view.borrow_item(row) {
+                Some(&TreeEntry::Track(ref playlist_track, ..)) => {
+                    filter::matches(&playlist_track.track, query)
+                }
+                _ => false,
+            })
+            .collect();
+        if let Some(target) =
+            filter::next_match(matches.len(), current, forward, |row| matches[row])
+        {
+            tree_view.set_selected_row(target);
+        }
+    });
+}
+
+// moves the focus to the track that is playing, if its playlist is expanded
+fn jump_to_playing(tree_view: &mut TreeView<TreeEntry>) {
+    let target = (0..tree_view.len()).find(|&row| match tree_view.borrow_item(row) {
+        Some(&TreeEntry::Track(_, _, _, playing, _)) => playing,
+        _ => false,
+    });
+    if let Some(target) = target {
+        tree_view.set_selected_row(target);
+    }
+}
+
+fn find_playlist(tree_view: &mut TreeView<TreeEntry>, id: &str) -> Option<usize> {
+    (0..tree_view.len()).find(|&row| match tree_view.borrow_item(row) {
+        Some(&TreeEntry::Playlist(ref entry)) => entry.playlist.id == id,
+        _ => false,
+    })
+}
+
+fn focused_playlist(siv: &mut Cursive) -> Option<PlaylistEntry> {
+    siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+        match tree_view.row().and_then(|row| tree_view.borrow_item(row)) {
+            Some(&TreeEntry::Playlist(ref entry)) => Some(entry.clone()),
+            _ => None,
+        }
+    })
+    .and_then(|entry| entry)
+}
+
+fn focused_track(siv: &mut Cursive) -> Option<FullTrack> {
+    siv.call_on_id(TREE_ID, |tree_view: &mut TreeView<TreeEntry>| {
+        match tree_view.row().and_then(|row| tree_view.borrow_item(row)) {
+            Some(&TreeEntry::Track(ref playlist_track, ..)) => Some(playlist_track.track.clone()),
+            _ => None,
+        }
+    })
+    .and_then(|track| track)
+}
+
+fn load_tracks(spotify: &Spotify, playlist: &SimplifiedPlaylist) -> Vec<PlaylistTrack> {
+    spotify.playlist_tracks(playlist).unwrap_or_else(|e| {
+        error!("could not load tracks of playlist {}: {}", playlist.name, e);
+        Vec::new()
+    })
+}
+
+fn report_error(siv: &mut Cursive, message: &str, e: Error) {
+    error!("{}: {}", message, e);
+    siv.add_layer(Dialog::info(format!("{}: {}", message, e)).title("Error"));
+}
+
+/// Shows a dialog to pick one of the playlists the user may modify and adds
+/// the given tracks to it.
+pub fn add_to_playlist(
+    siv: &mut Cursive,
+    spotify: Arc<Spotify>,
+    event_manager: EventManager,
+    tracks: Vec<FullTrack>,
+) {
+    let playlists = match spotify.current_user_playlists() {
+        Ok(playlists) => playlists,
+        Err(e) => {
+            report_error(siv, "could not load playlists", e);
+            return;
+        }
+    };
+
+    let mut select = SelectView::<SimplifiedPlaylist>::new();
+    for playlist in playlists {
+        if playlist.owner.id == spotify.user || playlist.collaborative {
+            select.add_item(playlist.name.clone(), playlist);
+        }
+    }
+
+    let track_ids: Vec<String> = tracks.iter().map(|track| track.id.clone()).collect();
+    select.set_on_submit(move |siv, playlist: &SimplifiedPlaylist| {
+        siv.pop_layer();
+        match spotify.playlist_add_tracks(playlist, &track_ids) {
+            Ok(snapshot_id) => {
+                event_manager.send(Event::PlaylistUpdate(playlist.id.clone(), snapshot_id))
+            }
+            Err(e) => report_error(siv, &format!("could not add to {}", playlist.name), e),
+        }
+    });
+
+    siv.add_layer(
+        Dialog::around(select.scrollable())
+            .title("Add to playlist")
+            .dismiss_button("Cancel"),
+    );
+}
+
+/// Asks for a name and creates a new playlist containing the given tracks.
+pub fn save_as_playlist(
+    siv: &mut Cursive,
+    spotify: Arc<Spotify>,
+    event_manager: EventManager,
+    tracks: Vec<FullTrack>,
+) {
+    let track_ids: Vec<String> = tracks.iter().map(|track| track.id.clone()).collect();
+    let dialog = name_dialog("New playlist", "", move |siv, name| {
+        match spotify.playlist_create(name, &track_ids) {
+            Ok(()) => event_manager.send(Event::PlaylistsUpdate),
+            Err(e) => report_error(siv, "could not create playlist", e),
+        }
+    });
+    siv.add_layer(dialog);
+}
+
+const NAME_ID: &str = "playlist_name";
+
+// dialog asking for a playlist name, which is passed on to `on_submit` unless
+// it is empty
+fn name_dialog<F>(title: &str, name: &str, on_submit: F) -> Dialog
+where
+    F: 'static + Fn(&mut Cursive, &str),
+{
+    let on_submit = Rc::new(move |siv: &mut Cursive, name: &str| {
+        siv.pop_layer();
+        if !name.trim().is_empty() {
+            on_submit(siv, name.trim());
+        }
+    });
+
+    let edit = {
+        let on_submit = on_submit.clone();
+        EditView::new()
+            .content(name)
+            .on_submit(move |siv, name| on_submit(siv, name))
+            .with_id(NAME_ID)
+            .fixed_width(40)
+    };
+
+    Dialog::around(edit)
+        .title(title)
+        .button("Ok", move |siv| {
+            let name = siv
+                .call_on_id(NAME_ID, |edit: &mut EditView| edit.get_content())
+                .unwrap();
+            on_submit(siv, &name);
+        })
+        .dismiss_button("Cancel")
 }
@@ -1,41 +1,71 @@
 use cursive::event::Key;
 use cursive::traits::Boxable;
 use cursive::traits::Identifiable;
-use cursive::traits::Scrollable;
 use cursive::views::*;
 use cursive::Cursive;
 
 use std::sync::Arc;
 use std::sync::Mutex;
 
+use rspotify::spotify::model::track::FullTrack;
+
 use crate::events::{Event, EventManager};
 use crate::queue::Queue;
+use crate::spotify::Spotify;
+use crate::ui::artist;
+use crate::ui::filter;
+use crate::ui::playlist;
+use crate::ui::tracklist::{Column, Row, TrackList};
 
 pub struct QueueView {
-    pub view: OnEventView<Panel<LinearLayout>>,
+    pub view: OnEventView<Panel<IdView<LinearLayout>>>,
 }
 
 const QUEUE_ID: &str = "queue_list";
+const LAYOUT_ID: &str = "queue_layout";
+const FILTER_ID: &str = "queue_filter";
 
 impl QueueView {
-    pub fn new(queue: Arc<Mutex<Queue>>, event_manager: EventManager) -> QueueView {
-        // let queuelist = ListView::new().with_id(QUEUE_ID);
-        let queuelist = SelectView::<String>::new().with_id(QUEUE_ID);
-
-        let layout = LinearLayout::vertical().child(
-            queuelist
-                .full_width()
-                .scrollable()
-                .full_width()
-                .full_height(),
-        );
+    pub fn new(
+        queue: Arc<Mutex<Queue>>,
+        spotify: Arc<Spotify>,
+        event_manager: EventManager,
+    ) -> QueueView {
+        // the ids of the rows are the indices of the tracks in the queue, as
+        // the list may be filtered or sorted
+        let mut queuelist = TrackList::new(&[
+            Column::Title,
+            Column::Artist,
+            Column::Album,
+            Column::Duration,
+        ]);
+        queuelist.show_playing(spotify.clone());
+
+        {
+            let queue = queue.clone();
+            let event_manager = event_manager.clone();
+            // <enter> dequeues the selected track
+            queuelist.set_on_submit(move |_siv, selected_id| {
+                let track = queue
+                    .lock()
+                    .unwrap()
+                    .remove(selected_id)
+                    .expect("could not dequeue track");
+                event_manager.send(Event::Play(track));
+                event_manager.send(Event::QueueUpdate);
+            });
+        }
+
+        let layout = LinearLayout::vertical()
+            .child(queuelist.with_id(QUEUE_ID).full_width().full_height())
+            .with_id(LAYOUT_ID);
         let panel = Panel::new(layout).title("Queue");
 
         let mut queuelist = OnEventView::new(panel);
 
         {
             let queue = queue.clone();
-            // <d> removes the selected track without playing it.
+            // <c> clears the queue
             queuelist.set_on_event('c', move |_cursive| {
                 queue.lock().unwrap().clear();
             });
@@ -44,64 +74,187 @@ impl QueueView {
         {
             let queue = queue.clone();
             let event_manager = event_manager.clone();
-            // <enter> dequeues the selected track
-            queuelist.set_on_pre_event(Key::Enter, move |siv| {
-                siv.call_on_id(QUEUE_ID, |queuelist: &mut SelectView| {
-                    let selected_id = queuelist.selected_id().unwrap();
-                    let track = queue
-                        .lock()
-                        .unwrap()
-                        .remove(selected_id)
-                        .expect("could not dequeue track");
-                    event_manager.send(Event::Play(track));
+            // <d> removes the marked tracks or the selected one without
+            // playing them.
+            queuelist.set_on_event('d', move |siv| {
+                let ids = QueueView::target_ids(siv);
+                if !ids.is_empty() {
+                    queue.lock().unwrap().remove_all(&ids);
                     event_manager.send(Event::QueueUpdate);
-                });
+                }
             });
         }
 
         {
             let queue = queue.clone();
             let event_manager = event_manager.clone();
-            // <d> removes the selected track without playing it.
-            queuelist.set_on_event('d', move |siv| {
-                siv.call_on_id(QUEUE_ID, |queuelist: &mut SelectView| {
-                    let selected_id = queuelist.selected_id().unwrap();
-                    queue
-                        .lock()
-                        .unwrap()
-                        .remove(selected_id)
-                        .expect("could not dequeue track");
-                    event_manager.send(Event::QueueUpdate);
+            // <A> shows the artist of the selected track
+            queuelist.set_on_event('A', move |siv| {
+                if let Some(track) = QueueView::selected_track(siv, &queue) {
+                    artist::select_artist(siv, &event_manager, &track.artists);
+                }
+            });
+        }
+
+        {
+            let queue = queue.clone();
+            let event_manager = event_manager.clone();
+            // <L> shows the album of the selected track
+            queuelist.set_on_event('L', move |siv| {
+                if let Some(track) = QueueView::selected_track(siv, &queue) {
+                    event_manager.send(Event::ShowAlbum(track.album.id));
+                }
+            });
+        }
+
+        {
+            let queue = queue.clone();
+            let spotify = spotify.clone();
+            let event_manager = event_manager.clone();
+            // <s> saves the marked tracks or the whole queue as a new playlist
+            queuelist.set_on_event('s', move |siv| {
+                let marked = siv
+                    .call_on_id(QUEUE_ID, |queuelist: &mut TrackList| {
+                        let marked = queuelist.marked_ids();
+                        queuelist.clear_marks();
+                        marked
+                    })
+                    .unwrap_or_default();
+                let tracks: Vec<_> = if marked.is_empty() {
+                    queue.lock().unwrap().iter().cloned().collect()
+                } else {
+                    QueueView::tracks(&queue, &marked)
+                };
+                if !tracks.is_empty() {
+                    playlist::save_as_playlist(
+                        siv,
+                        spotify.clone(),
+                        event_manager.clone(),
+                        tracks,
+                    );
+                }
+            });
+        }
+
+        {
+            let queue = queue.clone();
+            let event_manager = event_manager.clone();
+            // <p> adds the marked tracks or the selected one to a playlist
+            queuelist.set_on_event('p', move |siv| {
+                let tracks = QueueView::tracks(&queue, &QueueView::target_ids(siv));
+                if !tracks.is_empty() {
+                    playlist::add_to_playlist(siv, spotify.clone(), event_manager.clone(), tracks);
+                }
+            });
+        }
+
+        // <o> jumps to the track that is playing
+        queuelist.set_on_event('o', |siv| {
+            siv.call_on_id(QUEUE_ID, |queuelist: &mut TrackList| queuelist.select_playing());
+        });
+
+        let last_query = filter::LastQuery::default();
+
+        {
+            let queue = queue.clone();
+            let last_query = last_query.clone();
+            // </> filters the queue, <esc> removes the filter again
+            queuelist.set_on_event('/', move |siv| {
+                let queue = queue.clone();
+                filter::open(
+                    siv,
+                    LAYOUT_ID,
+                    FILTER_ID,
+                    QUEUE_ID,
+                    &last_query,
+                    move |siv, _| {
+                        QueueView::fill(siv, &queue);
+                    },
+                );
+            });
+        }
+
+        {
+            let queue = queue.clone();
+            queuelist.set_on_event(Key::Esc, move |siv| {
+                let queue = queue.clone();
+                filter::close(siv, LAYOUT_ID, FILTER_ID, QUEUE_ID, &move |siv: &mut Cursive, _: &str| {
+                    QueueView::fill(siv, &queue);
                 });
             });
         }
 
+        // <n> and <N> jump to the next and previous track matching the last
+        // query, also once the filter is closed
+        for &(key, forward) in &[('n', true), ('N', false)] {
+            let last_query = last_query.clone();
+            queuelist.set_on_event(key, move |siv| {
+                let query = last_query.get();
+                if !query.is_empty() {
+                    siv.call_on_id(QUEUE_ID, |queuelist: &mut TrackList| {
+                        queuelist.select_match(&query, forward)
+                    });
+                }
+            });
+        }
+
         QueueView { view: queuelist }
     }
 
+    fn selected_id(siv: &mut Cursive) -> Option<usize> {
+        siv.call_on_id(QUEUE_ID, |queuelist: &mut TrackList| queuelist.selected_id())
+            .and_then(|selected_id| selected_id)
+    }
+
+    // returns the marked tracks or the selected one, and removes the marks
+    // as the tracks are about to be acted upon
+    fn target_ids(siv: &mut Cursive) -> Vec<usize> {
+        siv.call_on_id(QUEUE_ID, |queuelist: &mut TrackList| {
+            let ids = queuelist.target_ids();
+            queuelist.clear_marks();
+            ids
+        })
+        .unwrap_or_default()
+    }
+
+    fn tracks(queue: &Mutex<Queue>, ids: &[usize]) -> Vec<FullTrack> {
+        let queue = queue.lock().unwrap();
+        ids.iter()
+            .filter_map(|&id| queue.iter().nth(id).cloned())
+            .collect()
+    }
+
+    fn selected_track(siv: &mut Cursive, queue: &Mutex<Queue>) -> Option<FullTrack> {
+        QueueView::selected_id(siv)
+            .and_then(|selected_id| queue.lock().unwrap().iter().nth(selected_id).cloned())
+    }
+
     pub fn redraw(s: &mut Cursive, queue: Arc<Mutex<Queue>>) {
-        s.call_on_id(QUEUE_ID, |queuelist: &mut SelectView| {
-            let selected_id = queuelist.selected_id();
-            queuelist.clear();
+        // the ids of the rows change along with the queue, so they can't be
+        // marked anymore
+        s.call_on_id(QUEUE_ID, |queuelist: &mut TrackList| queuelist.clear_marks());
+        QueueView::fill(s, &queue);
+    }
 
-            let queue = queue.lock().unwrap();
-            for track in queue.iter() {
-                let label = format!(
-                    "{} - {}",
-                    track.name,
-                    track
-                        .artists
-                        .iter()
-                        .map(|a| a.name.clone())
-                        .collect::<Vec<String>>()
-                        .join(", ")
-                );
-                queuelist.add_item(label, String::new());
-            }
+    // shows the tracks of the queue that match the filter
+    fn fill(s: &mut Cursive, queue: &Mutex<Queue>) {
+        let query = filter::query(s, FILTER_ID);
+        s.call_on_id(QUEUE_ID, |queuelist: &mut TrackList| {
+            let selected = queuelist.selected_index();
 
-            if let Some(selected_id) = selected_id {
-                queuelist.set_selection(selected_id);
-            }
+            let queue = queue.lock().unwrap();
+            let rows = queue
+                .iter()
+                .enumerate()
+                .filter(|&(_, track)| filter::matches(track, &query))
+                .map(|(i, track)| Row {
+                    id: i,
+                    track: track.clone(),
+                    added_at: None,
+                })
+                .collect();
+            queuelist.set_rows(rows);
+            queuelist.set_selected_index(selected);
         });
     }
 }
@@ -0,0 +1,175 @@
+use cursive::traits::View;
+use cursive::Cursive;
+use cursive::ScreenId;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::events::EventManager;
+use crate::ui::layout::Layout;
+use crate::ui::statusbar::StatusBar;
+use crate::ui::tabbar::TabBar;
+
+// a step of the navigation, which can be undone and redone
+enum Step {
+    // switched screens, holds the screen on the other side of the step
+    Switch(usize),
+    // a view was shown on top of a screen, e.g. an album page, making it the
+    // given number of layers high. Once the step is undone, the view is kept
+    // to be shown again.
+    Push(usize, usize, Option<Box<dyn View>>),
+}
+
+/// Keeps track of the screens, the one that is shown and the way the user
+/// navigated there. Screens are numbered in the order they were added, which
+/// is also the order of the tabs and their function keys.
+pub struct ScreenManager {
+    screens: Vec<ScreenId>,
+    titles: Rc<RefCell<Vec<String>>>,
+    active: usize,
+
+    back: Vec<Step>,
+    forward: Vec<Step>,
+
+    statusbar: Box<dyn Fn() -> StatusBar>,
+    event_manager: EventManager,
+}
+
+impl ScreenManager {
+    pub fn new<F: Fn() -> StatusBar + 'static>(
+        statusbar: F,
+        event_manager: EventManager,
+    ) -> ScreenManager {
+        ScreenManager {
+            screens: Vec::new(),
+            titles: Rc::new(RefCell::new(Vec::new())),
+            active: 0,
+            back: Vec::new(),
+            forward: Vec::new(),
+            statusbar: Box::new(statusbar),
+            event_manager,
+        }
+    }
+
+    /// Adds a screen showing `view` and returns its id.
+    pub fn add<V: View + 'static>(&mut self, siv: &mut Cursive, title: &str, view: V) -> ScreenId {
+        let index = self.screens.len();
+        let id = if index == 0 {
+            siv.active_screen()
+        } else {
+            siv.add_active_screen()
+        };
+        self.titles.borrow_mut().push(title.to_string());
+        siv.add_fullscreen_layer(self.layout(index, view));
+        self.screens.push(id);
+
+        // adding a screen activates it
+        self.active = index;
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.screens.len()
+    }
+
+    /// Shows the screen with the given number.
+    pub fn show(&mut self, siv: &mut Cursive, index: usize) {
+        if index >= self.len() {
+            return;
+        }
+        if index != self.active {
+            self.back.push(Step::Switch(self.active));
+            self.forward.clear();
+        }
+        self.switch(siv, index);
+    }
+
+    pub fn next(&mut self, siv: &mut Cursive) {
+        let index = (self.active + 1) % self.len();
+        self.show(siv, index);
+    }
+
+    pub fn previous(&mut self, siv: &mut Cursive) {
+        let index = (self.active + self.len() - 1) % self.len();
+        self.show(siv, index);
+    }
+
+    /// Shows a view on top of the active screen, e.g. an artist page.
+    pub fn push<V: View + 'static>(&mut self, siv: &mut Cursive, view: V) {
+        siv.add_fullscreen_layer(self.layout(self.active, view));
+        let layers = layers(siv);
+        self.back.push(Step::Push(self.active, layers, None));
+        self.forward.clear();
+    }
+
+    /// Undoes the last step of the navigation. Pages are only removed while
+    /// they are on top of their screen, so dialogs shown on top of them have
+    /// to be closed first.
+    pub fn back(&mut self, siv: &mut Cursive) {
+        match self.back.pop() {
+            Some(Step::Switch(index)) => {
+                self.forward.push(Step::Switch(self.active));
+                self.switch(siv, index);
+            }
+            Some(Step::Push(index, layers, _)) => {
+                self.switch(siv, index);
+                if self::layers(siv) != layers {
+                    self.back.push(Step::Push(index, layers, None));
+                    return;
+                }
+                let view = siv.pop_layer();
+                self.forward.push(Step::Push(index, layers, view));
+            }
+            None => (),
+        }
+    }
+
+    /// Closes the page on top of the active screen, if there is one. Unlike
+    /// `back`, this leaves the screens alone, even if others were shown since
+    /// the page was opened.
+    pub fn close_page(&mut self, siv: &mut Cursive) {
+        let (active, layers) = (self.active, layers(siv));
+        let step = self.back.iter().rposition(|step| match *step {
+            Step::Push(index, pushed, _) => index == active && pushed == layers,
+            Step::Switch(_) => false,
+        });
+        if let Some(step) = step {
+            self.back.remove(step);
+            let view = siv.pop_layer();
+            self.forward.push(Step::Push(active, layers, view));
+        }
+    }
+
+    /// Redoes the last step undone by `back` or `close_page`.
+    pub fn forward(&mut self, siv: &mut Cursive) {
+        match self.forward.pop() {
+            Some(Step::Switch(index)) => {
+                self.back.push(Step::Switch(self.active));
+                self.switch(siv, index);
+            }
+            Some(Step::Push(index, _, view)) => {
+                self.switch(siv, index);
+                if let Some(view) = view {
+                    siv.add_fullscreen_layer(view);
+                }
+                let layers = layers(siv);
+                self.back.push(Step::Push(index, layers, None));
+            }
+            None => (),
+        }
+    }
+
+    fn switch(&mut self, siv: &mut Cursive, index: usize) {
+        self.active = index;
+        siv.set_screen(self.screens[index]);
+    }
+
+    fn layout<V: View + 'static>(&self, index: usize, view: V) -> Layout {
+        let tabbar = TabBar::new(self.titles.clone(), index, self.event_manager.clone());
+        Layout::new(tabbar, view, (self.statusbar)())
+    }
+}
+
+// returns the number of layers of the active screen
+fn layers(siv: &Cursive) -> usize {
+    siv.screen().layer_sizes().len()
+}
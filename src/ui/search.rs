@@ -7,68 +7,245 @@ use cursive::Cursive;
 use std::sync::Arc;
 use std::sync::Mutex;
 
-use librespot::core::spotify_id::SpotifyId;
+use rspotify::spotify::model::track::FullTrack;
 
+use crate::events::{Event, EventManager};
 use crate::queue::Queue;
 use crate::spotify::Spotify;
-use crate::ui::trackbutton::TrackButton;
+use crate::ui::artist;
+use crate::ui::filter;
+use crate::ui::playlist;
+use crate::ui::tracklist::{Column, Row, TrackList};
 
 pub struct SearchView {
-    pub view: Panel<LinearLayout>,
+    pub view: OnEventView<Panel<IdView<LinearLayout>>>,
+}
+
+const LAYOUT_ID: &str = "search_layout";
+const FILTER_ID: &str = "search_filter";
+const RESULTS_ID: &str = "search_results";
+
+#[derive(Clone)]
+struct SearchContext {
+    spotify: Arc<Spotify>,
+    queue: Arc<Mutex<Queue>>,
+    event_manager: EventManager,
+    results: Arc<Mutex<Vec<FullTrack>>>,
 }
 
 impl SearchView {
-    fn search_handler(
-        s: &mut Cursive,
-        input: &str,
-        spotify: Arc<Spotify>,
-        queue: Arc<Mutex<Queue>>,
-    ) {
-        let mut results: ViewRef<ListView> = s.find_id("search_results").unwrap();
-        let tracks = spotify.search(input, 50, 0);
-
-        results.clear();
-
-        if let Ok(tracks) = tracks {
-            for track in tracks.tracks.items {
-                let s = spotify.clone();
-                let trackid = SpotifyId::from_base62(&track.id).expect("could not load track");
-                let mut button = TrackButton::new(&track);
-
-                // <enter> plays the selected track
-                button.add_callback(Key::Enter, move |_cursive| {
-                    s.load(trackid);
-                    s.play();
-                });
-
-                // <space> queues the selected track
-                let queue = queue.clone();
-                button.add_callback(' ', move |_cursive| {
-                    let mut queue = queue.lock().unwrap();
-                    queue.enqueue(track.clone());
-                });
-
-                results.add_child("", button);
-            }
+    fn search_handler(s: &mut Cursive, input: &str, context: &SearchContext) {
+        match context.spotify.search(input, 50, 0) {
+            Ok(tracks) => *context.results.lock().unwrap() = tracks.tracks.items,
+            Err(_) => context.results.lock().unwrap().clear(),
         }
+        SearchView::show_results(s, context);
+    }
+
+    // fills the result list with the results of the last search that match
+    // the current filter
+    fn show_results(s: &mut Cursive, context: &SearchContext) {
+        let query = filter::query(s, FILTER_ID);
+        let tracks = context.results.lock().unwrap();
+        let rows = tracks
+            .iter()
+            .enumerate()
+            .filter(|&(_, track)| filter::matches(track, &query))
+            .map(|(i, track)| Row {
+                id: i,
+                track: track.clone(),
+                added_at: None,
+            })
+            .collect();
+
+        s.call_on_id(RESULTS_ID, |results: &mut TrackList| results.set_rows(rows));
+    }
+
+    fn selected_track(s: &mut Cursive, context: &SearchContext) -> Option<FullTrack> {
+        s.call_on_id(RESULTS_ID, |results: &mut TrackList| results.selected_id())
+            .and_then(|selected_id| selected_id)
+            .and_then(|i| context.results.lock().unwrap().get(i).cloned())
     }
 
-    pub fn new(spotify: Arc<Spotify>, queue: Arc<Mutex<Queue>>) -> SearchView {
-        let searchfield = EditView::new()
-            .on_submit(move |s, input| {
-                SearchView::search_handler(s, input, spotify.clone(), queue.clone());
+    // returns the marked tracks, or the selected one if none are marked, and
+    // removes the marks as the tracks are about to be acted upon
+    fn target_tracks(s: &mut Cursive, context: &SearchContext) -> Vec<FullTrack> {
+        let ids = s
+            .call_on_id(RESULTS_ID, |results: &mut TrackList| {
+                let ids = results.target_ids();
+                results.clear_marks();
+                ids
             })
-            .with_id("search_edit")
-            .full_width()
-            .fixed_height(1);
-        let results = ListView::new().with_id("search_results").full_width();
-        let scrollable = ScrollView::new(results).full_width().full_height();
+            .unwrap_or_default();
+        let tracks = context.results.lock().unwrap();
+        ids.into_iter()
+            .filter_map(|i| tracks.get(i).cloned())
+            .collect()
+    }
+
+    pub fn new(
+        spotify: Arc<Spotify>,
+        queue: Arc<Mutex<Queue>>,
+        event_manager: EventManager,
+    ) -> SearchView {
+        let context = SearchContext {
+            spotify,
+            queue,
+            event_manager,
+            results: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let searchfield = {
+            let context = context.clone();
+            EditView::new()
+                .on_submit(move |s, input| {
+                    SearchView::search_handler(s, input, &context);
+                })
+                .with_id("search_edit")
+                .full_width()
+                .fixed_height(1)
+        };
+        let mut results = TrackList::new(&[
+            Column::Title,
+            Column::Artist,
+            Column::Album,
+            Column::Duration,
+            Column::Popularity,
+        ]);
+
+        results.show_playing(context.spotify.clone());
+
+        // <enter> plays the selected track
+        {
+            let context = context.clone();
+            results.set_on_submit(move |_s, i| {
+                if let Some(track) = context.results.lock().unwrap().get(i) {
+                    context.event_manager.send(Event::Play(track.clone()));
+                }
+            });
+        }
+
+        let results = results.with_id(RESULTS_ID).full_width().full_height();
         let layout = LinearLayout::new(Orientation::Vertical)
             .child(searchfield)
-            .child(scrollable);
+            .child(results)
+            .with_id(LAYOUT_ID);
         let rootpanel = Panel::new(layout).title("Search");
-        return SearchView {
-            view: rootpanel,
-        };
+        let mut view = OnEventView::new(rootpanel);
+
+        // <A> shows the artist of the selected track
+        {
+            let context = context.clone();
+            view.set_on_event('A', move |s| {
+                if let Some(track) = SearchView::selected_track(s, &context) {
+                    artist::select_artist(s, &context.event_manager, &track.artists);
+                }
+            });
+        }
+
+        // <L> shows the album of the selected track
+        {
+            let context = context.clone();
+            view.set_on_event('L', move |s| {
+                if let Some(track) = SearchView::selected_track(s, &context) {
+                    context.event_manager.send(Event::ShowAlbum(track.album.id));
+                }
+            });
+        }
+
+        // <a> queues the marked tracks or the selected one
+        {
+            let context = context.clone();
+            view.set_on_event('a', move |s| {
+                let tracks = SearchView::target_tracks(s, &context);
+                let mut queue = context.queue.lock().unwrap();
+                for track in tracks {
+                    queue.enqueue(track);
+                }
+            });
+        }
+
+        // <p> adds the marked tracks or the selected one to a playlist
+        {
+            let context = context.clone();
+            view.set_on_event('p', move |s| {
+                let tracks = SearchView::target_tracks(s, &context);
+                if !tracks.is_empty() {
+                    playlist::add_to_playlist(
+                        s,
+                        context.spotify.clone(),
+                        context.event_manager.clone(),
+                        tracks,
+                    );
+                }
+            });
+        }
+
+        // <s> saves the marked tracks or the selected one as a new playlist
+        {
+            let context = context.clone();
+            view.set_on_event('s', move |s| {
+                let tracks = SearchView::target_tracks(s, &context);
+                if !tracks.is_empty() {
+                    playlist::save_as_playlist(
+                        s,
+                        context.spotify.clone(),
+                        context.event_manager.clone(),
+                        tracks,
+                    );
+                }
+            });
+        }
+
+        // <o> jumps to the track that is playing
+        view.set_on_event('o', |s| {
+            s.call_on_id(RESULTS_ID, |results: &mut TrackList| results.select_playing());
+        });
+
+        // </> filters the results, <esc> removes the filter again
+        let last_query = filter::LastQuery::default();
+        {
+            let context = context.clone();
+            let last_query = last_query.clone();
+            view.set_on_event('/', move |s| {
+                let context = context.clone();
+                filter::open(
+                    s,
+                    LAYOUT_ID,
+                    FILTER_ID,
+                    RESULTS_ID,
+                    &last_query,
+                    move |s, _| {
+                        SearchView::show_results(s, &context);
+                    },
+                );
+            });
+        }
+        view.set_on_event(Key::Esc, move |s| {
+            let context = context.clone();
+            filter::close(
+                s,
+                LAYOUT_ID,
+                FILTER_ID,
+                RESULTS_ID,
+                &move |s: &mut Cursive, _: &str| SearchView::show_results(s, &context),
+            );
+        });
+
+        // <n> and <N> jump to the next and previous result matching the last
+        // query, also once the filter is closed
+        for &(key, forward) in &[('n', true), ('N', false)] {
+            let last_query = last_query.clone();
+            view.set_on_event(key, move |s| {
+                let query = last_query.get();
+                if !query.is_empty() {
+                    s.call_on_id(RESULTS_ID, |results: &mut TrackList| {
+                        results.select_match(&query, forward)
+                    });
+                }
+            });
+        }
+
+        SearchView { view }
     }
 }
@@ -0,0 +1,335 @@
+use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
+use cursive::theme::ColorStyle;
+use cursive::traits::View;
+use cursive::vec::Vec2;
+use cursive::Printer;
+use std::sync::{Arc, Mutex};
+use unicode_width::UnicodeWidthStr;
+
+use rspotify::spotify::model::track::FullTrack;
+
+use crate::events::{self, EventManager};
+use crate::spotify::PlayerState;
+use crate::theme;
+use crate::ui::tracklist::{format_duration, join_artists};
+
+pub const DEFAULT_FORMAT: &str = "{state} {artist} - {title} {bar} {elapsed} / {duration}";
+
+/// The playback status shown by the statusbars of all screens.
+pub struct Status {
+    track: Option<FullTrack>,
+    state: PlayerState,
+    elapsed_ms: u32,
+}
+
+impl Status {
+    pub fn new() -> Status {
+        Status {
+            track: None,
+            state: PlayerState::Stopped,
+            elapsed_ms: 0,
+        }
+    }
+
+    /// Follows the events changing the track or the state of the player.
+    pub fn handle(&mut self, event: &events::Event) {
+        match *event {
+            events::Event::SongChange(ref track) => {
+                self.track = Some(track.clone());
+                self.elapsed_ms = 0;
+            }
+            events::Event::PlayState(state) => {
+                self.state = state;
+                if state == PlayerState::Stopped {
+                    self.track = None;
+                    self.elapsed_ms = 0;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn set_elapsed(&mut self, ms: u32) {
+        self.elapsed_ms = ms;
+    }
+
+    pub fn track(&self) -> Option<&FullTrack> {
+        self.track.as_ref()
+    }
+
+    pub fn elapsed_ms(&self) -> u32 {
+        self.elapsed_ms
+    }
+
+    /// Returns the progress of the current track in percent.
+    pub fn progress(&self) -> usize {
+        match self.track {
+            Some(ref track) if track.duration_ms > 0 => {
+                (u64::from(self.elapsed_ms) * 100 / u64::from(track.duration_ms)).min(100) as usize
+            }
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    State,
+    Artist,
+    Title,
+    Album,
+    Elapsed,
+    Duration,
+    Volume,
+    Shuffle,
+    Repeat,
+    Bar,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Field> {
+        match name {
+            "state" => Some(Field::State),
+            "artist" => Some(Field::Artist),
+            "title" => Some(Field::Title),
+            "album" => Some(Field::Album),
+            "elapsed" => Some(Field::Elapsed),
+            "duration" => Some(Field::Duration),
+            "volume" => Some(Field::Volume),
+            "shuffle" => Some(Field::Shuffle),
+            "repeat" => Some(Field::Repeat),
+            "bar" => Some(Field::Bar),
+            _ => None,
+        }
+    }
+
+    fn text(self, status: &Status) -> String {
+        if self == Field::State {
+            return match status.state {
+                PlayerState::Playing => "▶",
+                PlayerState::Paused => "▮▮",
+                PlayerState::Stopped => "◼",
+            }
+            .to_string();
+        }
+
+        let track = match status.track {
+            Some(ref track) => track,
+            None => return String::new(),
+        };
+        match self {
+            Field::Artist => join_artists(track),
+            Field::Title => track.name.clone(),
+            Field::Album => track.album.name.clone(),
+            Field::Elapsed => format_duration(status.elapsed_ms),
+            Field::Duration => format_duration(track.duration_ms),
+            // there is no volume control, shuffle or repeat yet
+            _ => String::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Text(String),
+    Field(Field),
+}
+
+/// The contents of the statusbar, given as a string with placeholders like
+/// `{title}`. `{bar}` is replaced by a progress bar filling the remaining
+/// space, unknown placeholders are shown as they are.
+#[derive(Clone, Debug)]
+pub struct Format {
+    segments: Vec<Segment>,
+}
+
+impl Format {
+    pub fn parse(format: &str) -> Format {
+        let mut segments = Vec::new();
+        let mut rest = format;
+        while let Some(start) = rest.find('{') {
+            let field = rest[start..]
+                .find('}')
+                .and_then(|end| Field::parse(&rest[start + 1..start + end]).map(|f| (f, end)));
+            match field {
+                Some((field, end)) => {
+                    segments.push(Segment::Text(rest[..start].to_string()));
+                    segments.push(Segment::Field(field));
+                    rest = &rest[start + end + 1..];
+                }
+                None => {
+                    segments.push(Segment::Text(rest[..=start].to_string()));
+                    rest = &rest[start + 1..];
+                }
+            }
+        }
+        segments.push(Segment::Text(rest.to_string()));
+        Format { segments }
+    }
+
+    // returns the text left and right of the progress bar, or the whole text
+    // and `None` if there is no progress bar
+    fn render(&self, status: &Status) -> (String, Option<String>) {
+        // with nothing playing, only the state and the empty bar are shown
+        let segments: Vec<&Segment> = if status.track.is_some() {
+            self.segments.iter().collect()
+        } else {
+            self.segments
+                .iter()
+                .filter(|segment| match segment {
+                    Segment::Field(Field::State) | Segment::Field(Field::Bar) => true,
+                    _ => false,
+                })
+                .collect()
+        };
+
+        let mut left = String::new();
+        let mut right = None;
+        for segment in segments {
+            let text = match *segment {
+                Segment::Text(ref text) => text.clone(),
+                Segment::Field(Field::Bar) if right.is_none() => {
+                    right = Some(String::new());
+                    continue;
+                }
+                Segment::Field(field) => field.text(status),
+            };
+            match right {
+                Some(ref mut right) => right.push_str(&text),
+                None => left.push_str(&text),
+            }
+        }
+        if status.track.is_none() {
+            // keep the bar apart from the state
+            left.push(' ');
+        }
+        (left, right)
+    }
+}
+
+impl Default for Format {
+    fn default() -> Format {
+        Format::parse(DEFAULT_FORMAT)
+    }
+}
+
+/// The line at the bottom of every screen, showing the playback status in the
+/// configured format. Clicking the progress bar seeks to the respective
+/// position within the track.
+pub struct StatusBar {
+    status: Arc<Mutex<Status>>,
+    format: Format,
+    event_manager: EventManager,
+
+    // columns covered by the progress bar, as of the last layout
+    bar: (usize, usize),
+}
+
+impl StatusBar {
+    pub fn new(
+        status: Arc<Mutex<Status>>,
+        format: Format,
+        event_manager: EventManager,
+    ) -> StatusBar {
+        StatusBar {
+            status,
+            format,
+            event_manager,
+            bar: (0, 0),
+        }
+    }
+
+    // returns the start and width of the progress bar, which takes up the
+    // space between the two texts
+    fn bar_area(&self, width: usize) -> (usize, usize) {
+        match self.format.render(&self.status.lock().unwrap()) {
+            (left, Some(right)) => {
+                let start = left.width();
+                let end = width.saturating_sub(right.width());
+                (start, end.saturating_sub(start))
+            }
+            (_, None) => (0, 0),
+        }
+    }
+
+    fn draw_status(&self, printer: &Printer<'_, '_>) {
+        printer.print_hline((0, 0), printer.size.x, " ");
+
+        let status = self.status.lock().unwrap();
+        let (left, right) = self.format.render(&status);
+        printer.print((0, 0), &left);
+
+        let right = match right {
+            Some(right) => right,
+            None => return,
+        };
+        printer.print((printer.size.x.saturating_sub(right.width()), 0), &right);
+
+        let (start, width) = self.bar;
+        if width == 0 {
+            return;
+        }
+
+        let value = status.progress();
+        let label = format!("{} %", value);
+        let label_start = width.saturating_sub(label.len()) / 2;
+        let bar: String = (0..width)
+            .map(|x| {
+                x.checked_sub(label_start)
+                    .and_then(|i| label.chars().nth(i))
+                    .unwrap_or(' ')
+            })
+            .collect();
+
+        let filled = width * value / 100;
+        printer.print((start, 0), &bar);
+        printer.with_color(ColorStyle::highlight(), |printer| {
+            printer.print((start, 0), &bar[..filled]);
+        });
+    }
+}
+
+impl View for StatusBar {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        printer.with_color(theme::statusbar(printer.theme), |printer| {
+            self.draw_status(printer)
+        });
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        self.bar = self.bar_area(size.x);
+    }
+
+    fn needs_relayout(&self) -> bool {
+        // the texts around the progress bar change all the time
+        true
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        Vec2::new(constraint.x, 1)
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        if let Event::Mouse {
+            offset,
+            position,
+            event,
+        } = event
+        {
+            let (start, width) = self.bar;
+            let seek = match event {
+                MouseEvent::Press(MouseButton::Left) | MouseEvent::Hold(MouseButton::Left) => true,
+                _ => false,
+            };
+            if let Some(position) = position.checked_sub(offset) {
+                if seek && position.x >= start && position.x < start + width {
+                    let percent = (position.x - start) * 100 / width;
+                    self.event_manager
+                        .send(events::Event::SeekToPercent(percent));
+                    return EventResult::Consumed(None);
+                }
+            }
+        }
+        EventResult::Ignored
+    }
+}
@@ -0,0 +1,82 @@
+use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
+use cursive::theme::ColorStyle;
+use cursive::traits::View;
+use cursive::vec::Vec2;
+use cursive::Printer;
+use std::cell::RefCell;
+use std::rc::Rc;
+use unicode_width::UnicodeWidthStr;
+
+use crate::events::{self, EventManager};
+
+/// The line at the top of a screen, listing all screens along with the keys
+/// showing them. Every screen has its own tab bar, so `active` never changes.
+pub struct TabBar {
+    titles: Rc<RefCell<Vec<String>>>,
+    active: usize,
+    event_manager: EventManager,
+}
+
+impl TabBar {
+    pub fn new(
+        titles: Rc<RefCell<Vec<String>>>,
+        active: usize,
+        event_manager: EventManager,
+    ) -> TabBar {
+        TabBar {
+            titles,
+            active,
+            event_manager,
+        }
+    }
+
+    fn labels(&self) -> Vec<String> {
+        self.titles
+            .borrow()
+            .iter()
+            .enumerate()
+            .map(|(i, title)| format!(" F{} {} ", i + 1, title))
+            .collect()
+    }
+}
+
+impl View for TabBar {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        let mut x = 0;
+        for (i, label) in self.labels().iter().enumerate() {
+            let style = if i == self.active {
+                ColorStyle::highlight()
+            } else {
+                ColorStyle::primary()
+            };
+            printer.with_color(style, |printer| printer.print((x, 0), label));
+            x += label.width();
+        }
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        Vec2::new(constraint.x, 1)
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        // clicking a tab shows its screen
+        if let Event::Mouse {
+            offset,
+            position,
+            event: MouseEvent::Press(MouseButton::Left),
+        } = event
+        {
+            if let Some(position) = position.checked_sub(offset) {
+                let mut x = 0;
+                for (i, label) in self.labels().iter().enumerate() {
+                    x += label.width();
+                    if position.x < x {
+                        self.event_manager.send(events::Event::ShowScreen(i));
+                        return EventResult::Consumed(None);
+                    }
+                }
+            }
+        }
+        EventResult::Ignored
+    }
+}
@@ -0,0 +1,1046 @@
+use cursive::align::HAlign;
+use cursive::direction::Direction;
+use cursive::event::{Callback, Event, EventResult, Key, MouseButton, MouseEvent};
+use cursive::theme::ColorStyle;
+use cursive::traits::View;
+use cursive::vec::Vec2;
+use cursive::view::ViewWrapper;
+use cursive::views::{IdView, OnEventView};
+use cursive::wrap_impl;
+use cursive::Cursive;
+use cursive::Printer;
+use cursive_tree_view::TreeView;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt::{self, Debug, Display};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use rspotify::spotify::model::artist::SimplifiedArtist;
+use rspotify::spotify::model::track::FullTrack;
+
+use crate::events::{self, EventManager};
+use crate::queue::Queue;
+use crate::spotify::Spotify;
+use crate::theme;
+use crate::ui::artist;
+use crate::ui::filter;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+    Number,
+    Title,
+    Artist,
+    Album,
+    Duration,
+    AddedAt,
+    Popularity,
+}
+
+impl Column {
+    fn header(self) -> &'static str {
+        match self {
+            Column::Number => "#",
+            Column::Title => "Title",
+            Column::Artist => "Artist",
+            Column::Album => "Album",
+            Column::Duration => "Time",
+            Column::AddedAt => "Added",
+            Column::Popularity => "Pop",
+        }
+    }
+
+    // columns with a fixed width, the others share the remaining space
+    fn fixed_width(self) -> Option<usize> {
+        match self {
+            Column::Number => Some(5),
+            Column::Duration => Some(7),
+            Column::AddedAt => Some(10),
+            Column::Popularity => Some(3),
+            _ => None,
+        }
+    }
+
+    fn weight(self) -> usize {
+        match self {
+            Column::Title => 3,
+            Column::Artist | Column::Album => 2,
+            _ => 0,
+        }
+    }
+
+    fn align(self) -> HAlign {
+        match self {
+            Column::Duration | Column::Popularity => HAlign::Right,
+            _ => HAlign::Left,
+        }
+    }
+
+    fn text(self, row: &Row) -> String {
+        let track = &row.track;
+        match self {
+            Column::Number if track.disc_number > 1 => {
+                format!("{}-{:02}", track.disc_number, track.track_number)
+            }
+            Column::Number => format!("{:02}", track.track_number),
+            Column::Title => track.name.clone(),
+            Column::Artist => join_artists(track),
+            Column::Album => track.album.name.clone(),
+            Column::Duration => format_duration(track.duration_ms),
+            Column::AddedAt => row.added_at.clone().unwrap_or_default(),
+            Column::Popularity => track.popularity.to_string(),
+        }
+    }
+
+    fn compare(self, a: &Row, b: &Row) -> Ordering {
+        match self {
+            Column::Number => (a.track.disc_number, a.track.track_number)
+                .cmp(&(b.track.disc_number, b.track.track_number)),
+            Column::Duration => a.track.duration_ms.cmp(&b.track.duration_ms),
+            Column::AddedAt => a.added_at.cmp(&b.added_at),
+            Column::Popularity => a.track.popularity.cmp(&b.track.popularity),
+            _ => self
+                .text(a)
+                .to_lowercase()
+                .cmp(&self.text(b).to_lowercase()),
+        }
+    }
+}
+
+/// Shown in front of the track that is playing.
+pub const PLAYING_MARKER: &str = "▶";
+
+// rows scrolled by a turn of the mouse wheel
+const SCROLL_ROWS: isize = 3;
+// longest time between the clicks of a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(500);
+
+/// A track shown in a list. `id` identifies the row to its owner, e.g. the
+/// index of the track in the queue.
+#[derive(Clone, Debug)]
+pub struct Row {
+    pub id: usize,
+    pub track: FullTrack,
+    pub added_at: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sort {
+    pub column: Column,
+    pub ascending: bool,
+}
+
+impl Sort {
+    /// Returns the sorting after selecting `column`: the first selection
+    /// sorts ascending, the second one descending and the third one restores
+    /// the original order.
+    pub fn toggle(current: Option<Sort>, column: Column) -> Option<Sort> {
+        match current {
+            Some(sort) if sort.column == column && sort.ascending => Some(Sort {
+                column,
+                ascending: false,
+            }),
+            Some(ref sort) if sort.column == column => None,
+            _ => Some(Sort {
+                column,
+                ascending: true,
+            }),
+        }
+    }
+
+    /// Sorts the rows, keeping the original order of equal rows.
+    pub fn apply(self, rows: &mut [Row]) {
+        rows.sort_by(|a, b| self.compare(a, b));
+    }
+
+    fn compare(self, a: &Row, b: &Row) -> Ordering {
+        let ordering = self.column.compare(a, b);
+        if self.ascending {
+            ordering
+        } else {
+            ordering.reverse()
+        }
+    }
+}
+
+/// Returns the column that the keys <1> to <9> sort by.
+pub fn sort_column(columns: &[Column], event: &Event) -> Option<Column> {
+    match *event {
+        Event::Char(c) => c
+            .to_digit(10)
+            .filter(|&digit| digit > 0)
+            .and_then(|digit| columns.get(digit as usize - 1).cloned()),
+        _ => None,
+    }
+}
+
+/// Lays out the columns of a row at a fixed width, for views that can't use
+/// a `TrackList`, like the trees of the playlist and library screens.
+pub fn label(row: &Row, columns: &[Column], width: usize) -> String {
+    let widths = column_widths(columns, width);
+    columns
+        .iter()
+        .zip(widths)
+        .map(|(column, width)| pad(&column.text(row), width, column.align()))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Like `label`, for tracks without further details.
+pub fn track_label(track: &FullTrack, columns: &[Column], width: usize) -> String {
+    let row = Row {
+        id: 0,
+        track: track.clone(),
+        added_at: None,
+    };
+    label(&row, columns, width)
+}
+
+// tree views indent their rows by two columns per level, and keep two
+// columns free for the scrollbar
+const TREE_INDENT: usize = 2;
+const TREE_SCROLLBAR: usize = 2;
+
+/// An entry of a tree wrapped in a `TreeLayout`. Tree views show their items
+/// by formatting them, so the entries are given the width of the tree and the
+/// track that is playing before the tree is laid out.
+pub trait TreeRow {
+    fn set_layout(&mut self, width: usize, playing: Option<&str>);
+}
+
+/// Passes the width of a tree and the track that is playing to the entries
+/// of its rows, see `TreeRow`.
+pub struct TreeLayout<T: TreeRow + Display + Debug + 'static> {
+    view: IdView<TreeView<T>>,
+    spotify: Option<Arc<Spotify>>,
+}
+
+impl<T: TreeRow + Display + Debug + 'static> TreeLayout<T> {
+    pub fn new(view: IdView<TreeView<T>>) -> TreeLayout<T> {
+        TreeLayout {
+            view,
+            spotify: None,
+        }
+    }
+
+    /// Marks the rows of the track that is playing.
+    pub fn show_playing(mut self, spotify: Arc<Spotify>) -> TreeLayout<T> {
+        self.spotify = Some(spotify);
+        self
+    }
+}
+
+impl<T: TreeRow + Display + Debug + 'static> ViewWrapper for TreeLayout<T> {
+    wrap_impl!(self.view: IdView<TreeView<T>>);
+
+    fn wrap_layout(&mut self, size: Vec2) {
+        let playing = self
+            .spotify
+            .as_ref()
+            .and_then(|spotify| spotify.current_track_id());
+        {
+            // only the rows of expanded entries can be reached, the others
+            // are updated once they are shown
+            let mut tree_view = self.view.get_mut();
+            for row in 0..tree_view.len() {
+                if let Some(entry) = tree_view.borrow_item_mut(row) {
+                    entry.set_layout(size.x, playing.as_ref().map(String::as_str));
+                }
+            }
+        }
+        self.view.layout(size);
+    }
+}
+
+/// Returns the width left for the label of a row at `depth` of a tree that
+/// is `width` columns wide, the top level being 0.
+pub fn tree_label_width(width: usize, depth: usize) -> usize {
+    width.saturating_sub((depth + 1) * TREE_INDENT + TREE_SCROLLBAR)
+}
+
+/// A track in a tree, laid out in columns with the marker in front of it if
+/// it is playing.
+#[derive(Clone, Debug)]
+pub struct TreeTrack {
+    pub track: FullTrack,
+    columns: &'static [Column],
+    // the depth the track is laid out for, which may be deeper than the one
+    // it is shown at so the tracks of all levels line up
+    depth: usize,
+    width: usize,
+    playing: bool,
+}
+
+impl TreeTrack {
+    pub fn new(track: FullTrack, columns: &'static [Column], depth: usize) -> TreeTrack {
+        TreeTrack {
+            track,
+            columns,
+            depth,
+            width: 0,
+            playing: false,
+        }
+    }
+}
+
+impl TreeRow for TreeTrack {
+    fn set_layout(&mut self, width: usize, playing: Option<&str>) {
+        self.width = width;
+        self.playing = playing == Some(self.track.id.as_str());
+    }
+}
+
+impl Display for TreeTrack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let marker = if self.playing { PLAYING_MARKER } else { " " };
+        let width = tree_label_width(self.width, self.depth).saturating_sub(2);
+        write!(
+            f,
+            "{} {}",
+            marker,
+            track_label(&self.track, self.columns, width)
+        )
+    }
+}
+
+/// An entry of a tree that shows tracks along with their albums or artists,
+/// so the trees can share the keys acting on the focused entry.
+pub trait TrackEntry: Display + Debug + 'static {
+    /// Returns the track of the entry, if it is one.
+    fn track(&self) -> Option<&FullTrack>;
+
+    /// Returns the tracks that <a> adds to the queue. Entries that contain
+    /// tracks fetch them, as they may not have been expanded.
+    fn tracks(&self, _spotify: &Spotify) -> Vec<FullTrack> {
+        self.track().cloned().into_iter().collect()
+    }
+
+    /// Returns the album that <L> opens.
+    fn album_id(&self) -> Option<String> {
+        self.track().map(|track| track.album.id.clone())
+    }
+
+    /// Returns the artists that <A> shows.
+    fn artists(&self) -> Vec<SimplifiedArtist> {
+        self.track()
+            .map(|track| track.artists.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Selects the first row of a track, if it is shown.
+pub fn select_track<T: TrackEntry>(tree_view: &mut TreeView<T>, id: &str) {
+    let target = (0..tree_view.len()).find(|&row| {
+        tree_view
+            .borrow_item(row)
+            .and_then(|entry| entry.track())
+            .map_or(false, |track| track.id == id)
+    });
+    if let Some(target) = target {
+        tree_view.set_selected_row(target);
+    }
+}
+
+/// Adds the keys acting on the focused entry of the tree with the id
+/// `tree_id`: <a> adds its tracks to the queue, <L> opens its album, <A>
+/// shows its artist and <o> jumps to the track that is playing.
+pub fn tree_keys<T: TrackEntry, V: View>(
+    view: V,
+    tree_id: &str,
+    spotify: Arc<Spotify>,
+    queue: Arc<Mutex<Queue>>,
+    event_manager: EventManager,
+) -> OnEventView<V> {
+    let mut view = OnEventView::new(view);
+
+    {
+        let tree_id = tree_id.to_string();
+        let spotify = spotify.clone();
+        view.set_on_event('a', move |siv| {
+            siv.call_on_id(&tree_id, |tree_view: &mut TreeView<T>| {
+                let tracks = tree_view
+                    .row()
+                    .and_then(|row| tree_view.borrow_item(row))
+                    .map(|entry| entry.tracks(&spotify))
+                    .unwrap_or_default();
+                let mut queue = queue.lock().unwrap();
+                for track in tracks {
+                    queue.enqueue(track);
+                }
+            });
+        });
+    }
+
+    {
+        let tree_id = tree_id.to_string();
+        let event_manager = event_manager.clone();
+        view.set_on_event('L', move |siv| {
+            let album_id = siv
+                .call_on_id(&tree_id, |tree_view: &mut TreeView<T>| {
+                    tree_view
+                        .row()
+                        .and_then(|row| tree_view.borrow_item(row))
+                        .and_then(|entry| entry.album_id())
+                })
+                .and_then(|album_id| album_id);
+            if let Some(album_id) = album_id {
+                event_manager.send(events::Event::ShowAlbum(album_id));
+            }
+        });
+    }
+
+    {
+        let tree_id = tree_id.to_string();
+        view.set_on_event('A', move |siv| {
+            let artists = siv
+                .call_on_id(&tree_id, |tree_view: &mut TreeView<T>| {
+                    tree_view
+                        .row()
+                        .and_then(|row| tree_view.borrow_item(row))
+                        .map(|entry| entry.artists())
+                })
+                .and_then(|artists| artists)
+                .unwrap_or_default();
+            artist::select_artist(siv, &event_manager, &artists);
+        });
+    }
+
+    // jumps to the track that is playing, if it is shown
+    let tree_id = tree_id.to_string();
+    view.set_on_event('o', move |siv| {
+        if let Some(id) = spotify.current_track_id() {
+            siv.call_on_id(&tree_id, |tree_view: &mut TreeView<T>| {
+                select_track(tree_view, &id);
+            });
+        }
+    });
+
+    view
+}
+
+// called with the id of the row that was submitted
+type SubmitCallback = Rc<dyn Fn(&mut Cursive, usize)>;
+
+/// A list of tracks laid out in columns, which can be sorted by pressing the
+/// number of a column. Rows are marked with <space>, or as a range by pressing
+/// <v> at both of its ends.
+pub struct TrackList {
+    columns: Vec<Column>,
+    // rows in the order they were added, `order` holds the indices of the
+    // rows in the order they are shown
+    rows: Vec<Row>,
+    order: Vec<usize>,
+    sort: Option<Sort>,
+
+    // ids of the marked rows and the start of a range that is being marked
+    marked: HashSet<usize>,
+    anchor: Option<usize>,
+
+    selected: usize,
+    offset: usize,
+    size: Vec2,
+
+    // the last click, to tell double clicks apart
+    last_click: Option<(usize, Instant)>,
+
+    // tells the track that is playing, to mark its row
+    spotify: Option<Arc<Spotify>>,
+
+    on_submit: Option<SubmitCallback>,
+}
+
+impl TrackList {
+    pub fn new(columns: &[Column]) -> TrackList {
+        TrackList {
+            columns: columns.to_vec(),
+            rows: Vec::new(),
+            order: Vec::new(),
+            sort: None,
+            marked: HashSet::new(),
+            anchor: None,
+            selected: 0,
+            offset: 0,
+            size: Vec2::zero(),
+            last_click: None,
+            spotify: None,
+            on_submit: None,
+        }
+    }
+
+    /// Sets the callback for <enter>, which receives the id of the selected
+    /// row.
+    pub fn set_on_submit<F>(&mut self, cb: F)
+    where
+        F: 'static + Fn(&mut Cursive, usize),
+    {
+        self.on_submit = Some(Rc::new(cb));
+    }
+
+    pub fn add_row(&mut self, id: usize, track: FullTrack, added_at: Option<String>) {
+        self.rows.push(Row {
+            id,
+            track,
+            added_at,
+        });
+        self.resort();
+    }
+
+    /// Replaces the rows, keeping the marks of the rows with the same ids.
+    pub fn set_rows(&mut self, rows: Vec<Row>) {
+        self.marked.retain(|id| rows.iter().any(|row| row.id == *id));
+        self.anchor = None;
+        self.rows = rows;
+        self.order.clear();
+        self.selected = 0;
+        self.offset = 0;
+        self.resort();
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn selected_id(&self) -> Option<usize> {
+        self.selected_row().map(|row| row.id)
+    }
+
+    pub fn selected_row(&self) -> Option<&Row> {
+        self.order.get(self.selected).map(|&i| &self.rows[i])
+    }
+
+    /// Returns the position of the selection among the shown rows.
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    pub fn set_selected_index(&mut self, index: usize) {
+        self.selected = index.min(self.len().saturating_sub(1));
+    }
+
+    /// Returns the ids of the marked rows in the order they are shown.
+    pub fn marked_ids(&self) -> Vec<usize> {
+        self.order
+            .iter()
+            .enumerate()
+            .filter(|&(index, &row)| self.is_marked(index, row))
+            .map(|(_, &row)| self.rows[row].id)
+            .collect()
+    }
+
+    /// Returns the ids of the rows an action applies to, which are the
+    /// marked rows or the selected one if none are marked.
+    pub fn target_ids(&self) -> Vec<usize> {
+        let marked = self.marked_ids();
+        if marked.is_empty() {
+            self.selected_id().into_iter().collect()
+        } else {
+            marked
+        }
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.anchor = None;
+    }
+
+    /// Marks the row of the track that is playing.
+    pub fn show_playing(&mut self, spotify: Arc<Spotify>) {
+        self.spotify = Some(spotify);
+    }
+
+    /// Selects the row of the track that is playing, if it is in the list.
+    pub fn select_playing(&mut self) -> bool {
+        let playing = match self.playing_id() {
+            Some(id) => id,
+            None => return false,
+        };
+        match self
+            .order
+            .iter()
+            .position(|&row| self.rows[row].track.id == playing)
+        {
+            Some(index) => {
+                self.selected = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Selects the next row after the selected one whose track matches the
+    /// query, or the previous one if `forward` is false, wrapping around at
+    /// the ends.
+    pub fn select_match(&mut self, query: &str, forward: bool) {
+        let target = filter::next_match(self.len(), self.selected, forward, |index| {
+            filter::matches(&self.rows[self.order[index]].track, query)
+        });
+        if let Some(target) = target {
+            self.selected = target;
+        }
+    }
+
+    fn playing_id(&self) -> Option<String> {
+        self.spotify
+            .as_ref()
+            .and_then(|spotify| spotify.current_track_id())
+    }
+
+    pub fn sort_by(&mut self, column: Column) {
+        self.sort = Sort::toggle(self.sort, column);
+        // the range refers to the rows as they were shown
+        self.anchor = None;
+        self.resort();
+    }
+
+    fn is_marked(&self, index: usize, row: usize) -> bool {
+        let in_range = match self.anchor {
+            Some(anchor) => {
+                index >= anchor.min(self.selected) && index <= anchor.max(self.selected)
+            }
+            None => false,
+        };
+        in_range || self.marked.contains(&self.rows[row].id)
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(id) = self.selected_id() {
+            if !self.marked.remove(&id) {
+                self.marked.insert(id);
+            }
+            self.move_selection(1);
+        }
+    }
+
+    // starts a range at the selected row, or marks the rows of the range
+    // when it is ended
+    fn toggle_range(&mut self) {
+        match self.anchor.take() {
+            Some(anchor) => {
+                let (start, end) = (anchor.min(self.selected), anchor.max(self.selected));
+                for &row in &self.order[start..=end] {
+                    self.marked.insert(self.rows[row].id);
+                }
+            }
+            None if !self.is_empty() => self.anchor = Some(self.selected),
+            None => (),
+        }
+    }
+
+    // restores the order of the rows after they or the sorting changed,
+    // keeping the selected row selected
+    fn resort(&mut self) {
+        let selected = self.order.get(self.selected).cloned();
+
+        let mut rows: Vec<(usize, &Row)> = self.rows.iter().enumerate().collect();
+        if let Some(sort) = self.sort {
+            rows.sort_by(|&(_, a), &(_, b)| sort.compare(a, b));
+        }
+        self.order = rows.into_iter().map(|(i, _)| i).collect();
+
+        if let Some(selected) = selected {
+            self.selected = self.order.iter().position(|&i| i == selected).unwrap_or(0);
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.is_empty() {
+            return;
+        }
+        let selected = self.selected as isize + delta;
+        self.selected = selected.max(0).min(self.len() as isize - 1) as usize;
+    }
+
+    fn submit(&self) -> EventResult {
+        match (self.on_submit.clone(), self.selected_id()) {
+            (Some(cb), Some(id)) => {
+                EventResult::Consumed(Some(Callback::from_fn(move |siv| cb(siv, id))))
+            }
+            _ => EventResult::Ignored,
+        }
+    }
+
+    fn on_mouse(&mut self, position: Vec2, event: MouseEvent) -> EventResult {
+        if position.x >= self.size.x || position.y >= self.size.y {
+            return EventResult::Ignored;
+        }
+
+        match event {
+            MouseEvent::WheelUp => self.move_selection(-SCROLL_ROWS),
+            MouseEvent::WheelDown => self.move_selection(SCROLL_ROWS),
+            // clicking a header sorts by its column
+            MouseEvent::Press(MouseButton::Left) if position.y == 0 => {
+                let columns = self.columns.clone();
+                let widths = column_widths(&columns, self.size.x);
+                let mut x = 0;
+                for (&column, width) in columns.iter().zip(widths) {
+                    if position.x < x + width {
+                        self.sort_by(column);
+                        break;
+                    }
+                    x += width + 1;
+                }
+            }
+            MouseEvent::Press(MouseButton::Left) => {
+                let index = self.offset + position.y - 1;
+                if index >= self.len() {
+                    return EventResult::Ignored;
+                }
+                self.selected = index;
+
+                let double_click = match self.last_click {
+                    Some((clicked, time)) => clicked == index && time.elapsed() < DOUBLE_CLICK,
+                    None => false,
+                };
+                if double_click {
+                    self.last_click = None;
+                    return self.submit();
+                }
+                self.last_click = Some((index, Instant::now()));
+            }
+            _ => return EventResult::Ignored,
+        }
+        EventResult::Consumed(None)
+    }
+
+    fn draw_row(&self, printer: &Printer<'_, '_>, y: usize, cells: &[String], widths: &[usize]) {
+        let mut x = 0;
+        for ((cell, &width), column) in cells.iter().zip(widths).zip(&self.columns) {
+            printer.print((x, y), &pad(cell, width, column.align()));
+            x += width + 1;
+        }
+    }
+}
+
+impl View for TrackList {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        if printer.size.x == 0 || printer.size.y == 0 {
+            return;
+        }
+
+        let widths = column_widths(&self.columns, printer.size.x);
+
+        // the header marks the column the list is sorted by
+        let headers: Vec<String> = self
+            .columns
+            .iter()
+            .map(|&column| match self.sort {
+                Some(sort) if sort.column == column => {
+                    format!("{} {}", column.header(), if sort.ascending { "▲" } else { "▼" })
+                }
+                _ => column.header().to_string(),
+            })
+            .collect();
+        printer.with_color(ColorStyle::title_primary(), |printer| {
+            self.draw_row(printer, 0, &headers, &widths);
+        });
+
+        let playing = self.playing_id();
+        let visible = self.order.iter().enumerate().skip(self.offset);
+        for (y, (i, &row)) in visible.take(printer.size.y - 1).enumerate() {
+            let is_playing = playing.as_ref() == Some(&self.rows[row].track.id);
+            let style = if i != self.selected {
+                if self.is_marked(i, row) {
+                    ColorStyle::secondary()
+                } else if is_playing {
+                    theme::playing(printer.theme)
+                } else {
+                    ColorStyle::primary()
+                }
+            } else if printer.focused {
+                ColorStyle::highlight()
+            } else {
+                ColorStyle::highlight_inactive()
+            };
+
+            let mut cells: Vec<String> = self
+                .columns
+                .iter()
+                .map(|column| column.text(&self.rows[row]))
+                .collect();
+            if is_playing {
+                if let Some(first) = cells.first_mut() {
+                    *first = format!("{} {}", PLAYING_MARKER, first);
+                }
+            }
+            printer.with_color(style, |printer| {
+                printer.print_hline((0, y + 1), printer.size.x, " ");
+                self.draw_row(printer, y + 1, &cells, &widths);
+            });
+        }
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        if let Some(column) = sort_column(&self.columns, &event) {
+            self.sort_by(column);
+            return EventResult::Consumed(None);
+        }
+
+        let page = self.size.y.saturating_sub(1).max(1) as isize;
+        match event {
+            Event::Mouse {
+                offset,
+                position,
+                event,
+            } => {
+                return match position.checked_sub(offset) {
+                    Some(position) => self.on_mouse(position, event),
+                    None => EventResult::Ignored,
+                };
+            }
+            Event::Key(Key::Up) if self.selected > 0 => self.move_selection(-1),
+            Event::Key(Key::Down) if self.selected + 1 < self.len() => self.move_selection(1),
+            Event::Key(Key::PageUp) => self.move_selection(-page),
+            Event::Key(Key::PageDown) => self.move_selection(page),
+            Event::Key(Key::Home) => self.selected = 0,
+            Event::Key(Key::End) => self.selected = self.len().saturating_sub(1),
+            Event::Key(Key::Enter) => return self.submit(),
+            Event::Char(' ') => self.toggle_mark(),
+            Event::Char('v') => self.toggle_range(),
+            // <esc> only removes the marks if there are any, it is left to
+            // the surrounding views otherwise
+            Event::Key(Key::Esc) if !self.marked.is_empty() || self.anchor.is_some() => {
+                self.clear_marks()
+            }
+            _ => return EventResult::Ignored,
+        }
+        EventResult::Consumed(None)
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        self.size = size;
+
+        // scroll just enough for the selection to be visible, the first line
+        // is taken by the header
+        let height = size.y.saturating_sub(1);
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if height > 0 && self.selected >= self.offset + height {
+            self.offset = self.selected + 1 - height;
+        }
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        Vec2::new(constraint.x, self.len() + 1)
+    }
+
+    fn take_focus(&mut self, _: Direction) -> bool {
+        true
+    }
+}
+
+// splits the width among the columns, separated by a space each
+fn column_widths(columns: &[Column], width: usize) -> Vec<usize> {
+    let fixed: usize = columns.iter().filter_map(|c| c.fixed_width()).sum();
+    let separators = columns.len().saturating_sub(1);
+    let remaining = width.saturating_sub(fixed + separators);
+    let weights: usize = columns.iter().map(|c| c.weight()).sum();
+
+    let mut widths: Vec<usize> = columns
+        .iter()
+        .map(|c| match c.fixed_width() {
+            Some(width) => width,
+            None if weights > 0 => remaining * c.weight() / weights,
+            None => 0,
+        })
+        .collect();
+
+    // the rounding leftovers go to the first flexible column
+    let used: usize = widths.iter().sum::<usize>() + separators;
+    if let Some(first) = columns.iter().position(|c| c.fixed_width().is_none()) {
+        widths[first] += width.saturating_sub(used);
+    }
+    widths
+}
+
+// shortens the text to the given display width, appending ".." if it had to
+// be cut, and pads it with spaces to fill the width
+fn pad(text: &str, width: usize, align: HAlign) -> String {
+    let text = if text.width() <= width {
+        text.to_string()
+    } else {
+        let ellipsis = if width >= 2 { ".." } else { "" };
+        let mut shortened = String::new();
+        let mut used = 0;
+        for c in text.chars() {
+            let w = c.width().unwrap_or(0);
+            if used + w > width - ellipsis.len() {
+                break;
+            }
+            shortened.push(c);
+            used += w;
+        }
+        shortened.push_str(ellipsis);
+        shortened
+    };
+
+    let padding = " ".repeat(width.saturating_sub(text.width()));
+    match align {
+        HAlign::Right => format!("{}{}", padding, text),
+        _ => format!("{}{}", text, padding),
+    }
+}
+
+pub fn join_artists(track: &FullTrack) -> String {
+    join_names(&track.artists)
+}
+
+/// Joins the names of the artists of an album or a track.
+pub fn join_names(artists: &[SimplifiedArtist]) -> String {
+    artists
+        .iter()
+        .map(|a| a.name.clone())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+pub fn format_duration(ms: u32) -> String {
+    let seconds = ms / 1000;
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn row(id: usize, name: &str, artist: &str) -> Row {
+        let mut track = fixtures::track();
+        track.name = name.to_string();
+        track.artists.truncate(1);
+        track.artists[0].name = artist.to_string();
+        Row {
+            id,
+            track,
+            added_at: None,
+        }
+    }
+
+    #[test]
+    fn column_widths_share_remaining_space_by_weight() {
+        let columns = [
+            Column::Number,
+            Column::Title,
+            Column::Artist,
+            Column::Duration,
+        ];
+        assert_eq!(column_widths(&columns, 40), vec![5, 15, 10, 7]);
+        // the rounding leftovers go to the title
+        assert_eq!(column_widths(&columns, 41), vec![5, 16, 10, 7]);
+        // fixed columns keep their width when there is no space left
+        assert_eq!(column_widths(&columns, 5), vec![5, 0, 0, 7]);
+        assert_eq!(column_widths(&[], 10), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn pad_fills_and_aligns() {
+        assert_eq!(pad("abc", 5, HAlign::Left), "abc  ");
+        assert_eq!(pad("abc", 5, HAlign::Right), "  abc");
+        assert_eq!(pad("abc", 3, HAlign::Left), "abc");
+    }
+
+    #[test]
+    fn pad_shortens_by_display_width() {
+        assert_eq!(pad("abcdef", 4, HAlign::Left), "ab..");
+        // the characters are two columns wide each
+        assert_eq!(pad("日本語", 6, HAlign::Left), "日本語");
+        assert_eq!(pad("日本語", 5, HAlign::Left), "日.. ");
+        assert_eq!(pad("日本語", 1, HAlign::Left), " ");
+        assert_eq!(pad("abc", 0, HAlign::Left), "");
+    }
+
+    #[test]
+    fn sort_toggles_through_ascending_descending_and_none() {
+        let ascending = Sort::toggle(None, Column::Title);
+        assert_eq!(
+            ascending,
+            Some(Sort {
+                column: Column::Title,
+                ascending: true,
+            })
+        );
+        let descending = Sort::toggle(ascending, Column::Title);
+        assert_eq!(
+            descending,
+            Some(Sort {
+                column: Column::Title,
+                ascending: false,
+            })
+        );
+        assert_eq!(Sort::toggle(descending, Column::Title), None);
+        // another column starts over
+        assert_eq!(
+            Sort::toggle(descending, Column::Artist),
+            Some(Sort {
+                column: Column::Artist,
+                ascending: true,
+            })
+        );
+    }
+
+    #[test]
+    fn sort_keeps_order_of_equal_rows() {
+        let mut rows = vec![
+            row(0, "b", "Y"),
+            row(1, "a", "x"),
+            row(2, "c", "Y"),
+            row(3, "d", "x"),
+        ];
+        let ids = |rows: &[Row]| rows.iter().map(|row| row.id).collect::<Vec<usize>>();
+
+        Sort {
+            column: Column::Artist,
+            ascending: true,
+        }
+        .apply(&mut rows);
+        assert_eq!(ids(&rows), vec![1, 3, 0, 2]);
+
+        Sort {
+            column: Column::Artist,
+            ascending: false,
+        }
+        .apply(&mut rows);
+        assert_eq!(ids(&rows), vec![0, 2, 1, 3]);
+    }
+
+    #[test]
+    fn sort_column_maps_number_keys() {
+        let columns = [Column::Title, Column::Artist];
+        assert_eq!(
+            sort_column(&columns, &Event::Char('1')),
+            Some(Column::Title)
+        );
+        assert_eq!(
+            sort_column(&columns, &Event::Char('2')),
+            Some(Column::Artist)
+        );
+        assert_eq!(sort_column(&columns, &Event::Char('3')), None);
+        assert_eq!(sort_column(&columns, &Event::Char('0')), None);
+        assert_eq!(sort_column(&columns, &Event::Char('a')), None);
+        assert_eq!(sort_column(&columns, &Event::Key(Key::Enter)), None);
+    }
+
+    #[test]
+    fn tree_tracks_are_laid_out_for_their_own_tree() {
+        let mut wide = TreeTrack::new(fixtures::track(), &[Column::Title], 0);
+        let mut narrow = wide.clone();
+        wide.set_layout(20, Some("track1"));
+        narrow.set_layout(10, None);
+        assert_eq!(wide.to_string(), "▶ The Title     ");
+        assert_eq!(narrow.to_string(), "  Th..");
+    }
+}
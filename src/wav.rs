@@ -0,0 +1,196 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+
+use librespot::playback::audio_backend::Sink;
+use log::error;
+
+const SAMPLE_RATE: u32 = 44_100;
+const CHANNELS: u16 = 2;
+const BITS_PER_SAMPLE: u16 = 16;
+const HEADER_LEN: u32 = 44;
+
+/// Returns an error if audio can't be written to `path`, so a backend
+/// writing to a file can be rejected before it is started. Pipes and devices
+/// aren't opened, as opening a pipe waits for its reader.
+pub fn check_device(path: &str) -> io::Result<()> {
+    match fs::metadata(path) {
+        Ok(ref metadata) if !metadata.is_file() => {
+            if metadata.permissions().readonly() {
+                Err(io::Error::new(io::ErrorKind::PermissionDenied, "read-only"))
+            } else {
+                Ok(())
+            }
+        }
+        _ => OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .map(|_| ()),
+    }
+}
+
+/// Writes the audio to a WAV file. The sizes in the header are updated
+/// whenever playback stops, so the file can be read at any point. If the
+/// file is a pipe, the sizes stay at their maximum, which players take as a
+/// stream of unknown length. If the file can't be written, the error is
+/// logged and the audio is dropped.
+pub struct WavSink {
+    file: Option<BufWriter<File>>,
+    data_len: u32,
+}
+
+impl WavSink {
+    pub fn open(device: Option<String>) -> Box<dyn Sink> {
+        let mut sink = WavSink {
+            file: None,
+            data_len: 0,
+        };
+        let path = match device {
+            Some(path) => path,
+            None => {
+                error!("the wav backend needs a device to write to");
+                return Box::new(sink);
+            }
+        };
+        match File::create(&path) {
+            Ok(file) => sink.file = Some(BufWriter::new(file)),
+            Err(e) => error!("could not create {}: {}", path, e),
+        }
+        if let Err(e) = sink.write_header(std::u32::MAX - HEADER_LEN) {
+            error!("could not write to {}: {}", path, e);
+            sink.file = None;
+        }
+        Box::new(sink)
+    }
+
+    fn write_header(&mut self, data_len: u32) -> io::Result<()> {
+        let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+        let file = match self.file {
+            Some(ref mut file) => file,
+            None => return Ok(()),
+        };
+        file.write_all(b"RIFF")?;
+        file.write_all(&(data_len.saturating_add(HEADER_LEN - 8)).to_le_bytes())?;
+        file.write_all(b"WAVEfmt ")?;
+        file.write_all(&16u32.to_le_bytes())?;
+        // PCM
+        file.write_all(&1u16.to_le_bytes())?;
+        file.write_all(&CHANNELS.to_le_bytes())?;
+        file.write_all(&SAMPLE_RATE.to_le_bytes())?;
+        file.write_all(&(SAMPLE_RATE * u32::from(block_align)).to_le_bytes())?;
+        file.write_all(&block_align.to_le_bytes())?;
+        file.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+        file.write_all(b"data")?;
+        file.write_all(&data_len.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+impl Sink for WavSink {
+    fn start(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn stop(&mut self) -> io::Result<()> {
+        let data_len = self.data_len;
+        let seekable = match self.file {
+            Some(ref mut file) => {
+                file.flush()?;
+                // pipes can't seek, and keep the header as it was written
+                // first
+                file.get_mut().seek(SeekFrom::Start(0)).is_ok()
+            }
+            None => return Ok(()),
+        };
+        if seekable {
+            self.write_header(data_len)?;
+            if let Some(ref mut file) = self.file {
+                file.flush()?;
+                file.get_mut().seek(SeekFrom::End(0))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[i16]) -> io::Result<()> {
+        let file = match self.file {
+            Some(ref mut file) => file,
+            None => return Ok(()),
+        };
+        for sample in data {
+            file.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_len = self.data_len.saturating_add(data.len() as u32 * 2);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_path(name: &str) -> String {
+        env::temp_dir()
+            .join(format!("ncspot-{}-{}.wav", name, std::process::id()))
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        let mut field = [0u8; 4];
+        field.copy_from_slice(&bytes[offset..offset + 4]);
+        u32::from_le_bytes(field)
+    }
+
+    #[test]
+    fn header_describes_stereo_pcm_of_unknown_length() {
+        let path = temp_path("header");
+        drop(WavSink::open(Some(path.clone())));
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(bytes.len(), HEADER_LEN as usize);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32_at(&bytes, 4), std::u32::MAX - 8);
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(
+            &bytes[16..36],
+            &[
+                16, 0, 0, 0, // length of the format chunk
+                1, 0, // PCM
+                2, 0, // channels
+                0x44, 0xac, 0, 0, // 44100 Hz
+                0x10, 0xb1, 2, 0, // 176400 bytes per second
+                4, 0, // bytes per frame
+                16, 0, // bits per sample
+            ]
+        );
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(u32_at(&bytes, 40), std::u32::MAX - HEADER_LEN);
+    }
+
+    #[test]
+    fn stop_writes_sizes_of_file() {
+        let path = temp_path("sizes");
+        let mut sink = WavSink::open(Some(path.clone()));
+        sink.start().unwrap();
+        sink.write(&[1, -1, 2, -2]).unwrap();
+        sink.stop().unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(u32_at(&bytes, 4), 36 + 8);
+        assert_eq!(u32_at(&bytes, 40), 8);
+        assert_eq!(&bytes[44..], &[1, 0, 0xff, 0xff, 2, 0, 0xfe, 0xff]);
+
+        // playback goes on at the end of the file
+        sink.start().unwrap();
+        sink.write(&[3, 3]).unwrap();
+        sink.stop().unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(bytes.len(), 44 + 12);
+        assert_eq!(u32_at(&bytes, 4), 36 + 12);
+        assert_eq!(u32_at(&bytes, 40), 12);
+    }
+}